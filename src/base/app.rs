@@ -1,12 +1,14 @@
 use std::future::Future;
 
-use anyhow::Result;
-
 use super::client::Client;
+use crate::error::Result;
 
 pub trait Application<C: Client> {
-    /// Sometimes need async to initialize the struct data
-    fn from_client(client: &C) -> impl Future<Output = Self>;
+    /// Sometimes need async to initialize the struct data,
+    /// an application which needs a login returns why it can't be built yet.
+    fn from_client(client: &C) -> impl Future<Output = Result<Self>>
+    where
+        Self: Sized;
     /// Default constructor for flexibility
     fn new() -> Self
     where
@@ -17,12 +19,12 @@ pub trait Application<C: Client> {
 }
 
 pub trait AppVisitor<C: Client> {
-    fn visit<T: Application<C>>(&self) -> impl Future<Output = T>;
+    fn visit<T: Application<C>>(&self) -> impl Future<Output = Result<T>>;
     fn try_restore<T: CachedApplication<C>>(&self) -> impl Future<Output = Option<T>>;
 }
 
 impl<C: Client> AppVisitor<C> for C {
-    async fn visit<T: Application<Self>>(&self) -> T {
+    async fn visit<T: Application<Self>>(&self) -> Result<T> {
         T::from_client(self).await
     }
 
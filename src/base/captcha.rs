@@ -0,0 +1,35 @@
+use std::{future::Future, pin::Pin};
+
+use crate::error::Result;
+
+pub type CaptchaFuture<'a> = Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+
+/// Reads the captcha the SSO demands after several failed logins.
+///
+/// The image is downloaded with the cookies of the login,
+/// a solver may show it to the user or run an OCR.
+pub trait CaptchaSolver: Send + Sync {
+    /// Return the text of the captcha image.
+    fn solve<'a>(&'a self, image: &'a [u8]) -> CaptchaFuture<'a>;
+}
+
+/// Ask a callback for the text of the captcha image.
+pub struct CallbackCaptchaSolver<F> {
+    callback: F,
+}
+
+impl<F> CallbackCaptchaSolver<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F, Fut> CaptchaSolver for CallbackCaptchaSolver<F>
+where
+    F: Fn(Vec<u8>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String>> + Send + 'static,
+{
+    fn solve<'a>(&'a self, image: &'a [u8]) -> CaptchaFuture<'a> {
+        Box::pin((self.callback)(image.to_vec()))
+    }
+}
@@ -1,25 +1,33 @@
+use reqwest::header::HeaderMap;
 use reqwest_cookie_store::CookieStoreMutex;
-use std::{collections::HashMap, sync::Arc};
+use std::{future::Future, sync::Arc};
 use tokio::sync::RwLock;
 
+use super::{
+    captcha::CaptchaSolver,
+    credentials::Secret,
+    endpoints::{DEFAULT_ENDPOINTS, Endpoints},
+    properties::Properties,
+    retry::{DEFAULT_RETRY_POLICY, RetryPolicy},
+    transport::Transport,
+};
+use crate::{
+    error::Result,
+    internals::{fields::DEFAULT_HEADERS, redirect::DEFAULT_MAX_REDIRECTS},
+};
+
 /// You must decide what account to use to invoke different method!
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+///
+/// The password may be left empty if the client reads it from a
+/// [`super::credentials::CredentialProvider`].
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
 pub struct Account {
     pub user: String,
-    pub password: String,
-}
-
-impl Default for Account {
-    fn default() -> Self {
-        Self {
-            user: Default::default(),
-            password: Default::default(),
-        }
-    }
+    pub password: Secret,
 }
 
 impl Account {
-    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+    pub fn new(user: impl Into<String>, password: impl Into<Secret>) -> Self {
         Self {
             user: user.into(),
             password: password.into(),
@@ -27,63 +35,44 @@ impl Account {
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum Property {
-    String(String),
-    I32(i32),
-    Bool(bool),
-    Str(&'static str),
-}
-
-impl Property {
-    pub fn get_bool(&self) -> Option<bool> {
-        match self {
-            Property::Bool(value) => Some(value.clone()),
-            _ => None,
-        }
+pub trait Client {
+    fn account(&self) -> Account;
+    fn reqwest_client(&self) -> reqwest::Client;
+    /// Sends every request of the applications, see [`Transport`].
+    fn transport(&self) -> Arc<dyn Transport> {
+        Arc::new(self.reqwest_client())
     }
-
-    pub fn get_bool_unwrap(&self) -> bool {
-        self.get_bool().unwrap()
+    fn cookies(&self) -> Arc<CookieStoreMutex>;
+    fn properties(&self) -> Arc<RwLock<Properties>>;
+    /// The password of [`Client::account`], only read by the login flows when they send it.
+    fn password(&self) -> impl Future<Output = Result<Secret>> {
+        async { Ok(self.account().password) }
     }
-
-    pub fn get_i32(&self) -> Option<i32> {
-        match self {
-            Property::I32(value) => Some(value.clone()),
-            _ => None,
-        }
+    /// Headers sent with every request, e.g. the `User-Agent`.
+    fn headers(&self) -> HeaderMap {
+        DEFAULT_HEADERS.clone()
     }
-
-    pub fn get_i32_unwrap(&self) -> i32 {
-        self.get_i32().unwrap()
+    /// Where the campus systems are, see [`Endpoints`].
+    fn endpoints(&self) -> &Endpoints {
+        &DEFAULT_ENDPOINTS
     }
-
-    pub fn get_string(&self) -> Option<String> {
-        match self {
-            Property::String(value) => Some(value.clone()),
-            _ => None,
-        }
+    /// How failed requests are retried, see [`RetryPolicy`].
+    fn retry_policy(&self) -> &RetryPolicy {
+        &DEFAULT_RETRY_POLICY
     }
-
-    pub fn get_string_unwrap(&self) -> String {
-        self.get_string().unwrap()
+    /// How many redirects the login flows follow before giving up with
+    /// [`crate::error::Error::TooManyRedirects`].
+    fn max_redirects(&self) -> usize {
+        DEFAULT_MAX_REDIRECTS
     }
-
-    pub fn get_str(&self) -> Option<&'static str> {
-        match self {
-            Property::Str(value) => Some(value),
-            _ => None,
-        }
+    /// Log in again and replay the request once when the session has expired,
+    /// otherwise [`crate::error::Error::SessionExpired`] is returned.
+    fn auto_relogin(&self) -> bool {
+        false
     }
-
-    pub fn get_str_unwrap(&self) -> &'static str {
-        self.get_str().unwrap()
+    /// Solves the captcha of the SSO login form,
+    /// without it a captcha is returned as [`crate::error::Error::CaptchaRequired`].
+    fn captcha_solver(&self) -> Option<Arc<dyn CaptchaSolver>> {
+        None
     }
 }
-
-pub trait Client {
-    fn account(&self) -> Account;
-    fn reqwest_client(&self) -> reqwest::Client;
-    fn cookies(&self) -> Arc<CookieStoreMutex>;
-    fn properties(&self) -> Arc<RwLock<HashMap<&'static str, Property>>>;
-}
@@ -0,0 +1,317 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::Pin,
+};
+
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+
+/// A password, wiped from memory on drop and never printed by [`Debug`].
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(Zeroizing::new(secret.into()))
+    }
+
+    /// Read the secret, don't keep the result longer than needed.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(\"***\")")
+    }
+}
+
+impl Hash for Secret {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+pub type CredentialFuture<'a> = Pin<Box<dyn Future<Output = Result<Secret>> + Send + 'a>>;
+
+/// Where a client reads the password of its account from.
+///
+/// The login flows ask for the password right before sending it,
+/// so a provider may prompt the user or read a store every time.
+pub trait CredentialProvider: Send + Sync {
+    fn password<'a>(&'a self, user: &'a str) -> CredentialFuture<'a>;
+}
+
+/// The password given when building the client.
+impl CredentialProvider for Secret {
+    fn password<'a>(&'a self, _user: &'a str) -> CredentialFuture<'a> {
+        Box::pin(async move { Ok(self.clone()) })
+    }
+}
+
+/// Read the password from an environment variable.
+#[derive(Debug, Clone)]
+pub struct EnvCredential {
+    var: String,
+}
+
+impl EnvCredential {
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+}
+
+impl CredentialProvider for EnvCredential {
+    fn password<'a>(&'a self, _user: &'a str) -> CredentialFuture<'a> {
+        Box::pin(async move {
+            std::env::var(&self.var)
+                .map(Secret::new)
+                .map_err(|e| Error::Credential(format!("Read `{}` failed: {}", self.var, e)))
+        })
+    }
+}
+
+/// Ask a callback for the password of the user, e.g. prompt in a terminal or a keyring.
+pub struct CallbackCredential<F> {
+    callback: F,
+}
+
+impl<F> CallbackCredential<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F, Fut> CredentialProvider for CallbackCredential<F>
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Secret>> + Send + 'static,
+{
+    fn password<'a>(&'a self, user: &'a str) -> CredentialFuture<'a> {
+        Box::pin((self.callback)(user.to_string()))
+    }
+}
+
+#[cfg(feature = "credential-file")]
+pub use file::{DEFAULT_PBKDF2_ITERATIONS, EncryptedFileCredential};
+
+#[cfg(feature = "credential-file")]
+mod file {
+    use std::path::PathBuf;
+
+    use aes_gcm::{
+        Aes256Gcm, KeyInit, Nonce,
+        aead::{Aead, Payload},
+    };
+    use base64::{Engine, prelude::BASE64_STANDARD};
+    use rand::Rng;
+    use sha2::Sha256;
+    use zeroize::Zeroizing;
+
+    use super::{CredentialFuture, CredentialProvider, Secret};
+    use crate::error::{Error, Result};
+
+    /// Default of [`EncryptedFileCredential::iterations`].
+    pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+    /// The most PBKDF2 iterations stored or loaded, a file asking for more is rejected.
+    pub const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+    const VERSION: u8 = 1;
+    const SALT_LEN: usize = 16;
+    const NONCE_LEN: usize = 12;
+    /// The version, the iterations, the salt and the nonce.
+    const HEADER_LEN: usize = 1 + 4 + SALT_LEN + NONCE_LEN;
+
+    /// A password stored in a file, encrypted with AES-256-GCM under a key
+    /// derived from a passphrase by PBKDF2-HMAC-SHA256.
+    ///
+    /// The file holds the base64 of a version byte, the iterations, the random salt,
+    /// the random nonce and the authenticated ciphertext,
+    /// create it with [`EncryptedFileCredential::store`].
+    /// A wrong passphrase and a modified file are both rejected.
+    pub struct EncryptedFileCredential {
+        path: PathBuf,
+        passphrase: Secret,
+        iterations: u32,
+    }
+
+    impl EncryptedFileCredential {
+        pub fn new(path: impl Into<PathBuf>, passphrase: &Secret) -> Self {
+            Self {
+                path: path.into(),
+                passphrase: passphrase.clone(),
+                iterations: DEFAULT_PBKDF2_ITERATIONS,
+            }
+        }
+
+        /// The PBKDF2 iterations of [`Self::store`], a stored file keeps its own,
+        /// default: [`DEFAULT_PBKDF2_ITERATIONS`], at most [`MAX_PBKDF2_ITERATIONS`]
+        pub fn iterations(mut self, iterations: u32) -> Self {
+            self.iterations = iterations.clamp(1, MAX_PBKDF2_ITERATIONS);
+            self
+        }
+
+        /// Encrypt the password into the file, replacing its content.
+        pub fn store(&self, password: &Secret) -> Result<()> {
+            let mut header = [0u8; HEADER_LEN];
+            header[0] = VERSION;
+            header[1..5].copy_from_slice(&self.iterations.to_be_bytes());
+            rand::rng().fill(&mut header[5..]);
+            let (salt, nonce) = header[5..].split_at(SALT_LEN);
+            let ciphertext = cipher(&self.passphrase, salt, self.iterations)
+                .encrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: password.expose().as_bytes(),
+                        aad: &header,
+                    },
+                )
+                .map_err(|_| Error::Credential("Encrypt password failed".into()))?;
+            let mut content = header.to_vec();
+            content.extend(ciphertext);
+            Ok(std::fs::write(&self.path, BASE64_STANDARD.encode(content))?)
+        }
+    }
+
+    fn cipher(passphrase: &Secret, salt: &[u8], iterations: u32) -> Aes256Gcm {
+        let mut key = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            passphrase.expose().as_bytes(),
+            salt,
+            iterations,
+            key.as_mut_slice(),
+        );
+        Aes256Gcm::new(key.as_slice().into())
+    }
+
+    fn decrypt(passphrase: &Secret, content: &str) -> Result<Secret> {
+        let content = BASE64_STANDARD
+            .decode(content.trim())
+            .map_err(|e| Error::Credential(format!("Decode credential file failed: {}", e)))?;
+        if content.len() <= HEADER_LEN {
+            return Err(Error::Credential("Credential file is too short".into()));
+        }
+        let (header, ciphertext) = content.split_at(HEADER_LEN);
+        if header[0] != VERSION {
+            return Err(Error::Credential(format!(
+                "Unknown credential file version {}",
+                header[0]
+            )));
+        }
+        let iterations = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        if iterations > MAX_PBKDF2_ITERATIONS {
+            return Err(Error::Credential(format!(
+                "Too many PBKDF2 iterations {} in the credential file",
+                iterations
+            )));
+        }
+        let (salt, nonce) = header[5..].split_at(SALT_LEN);
+        let plaintext = Zeroizing::new(
+            cipher(passphrase, salt, iterations.max(1))
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: header,
+                    },
+                )
+                .map_err(|_| {
+                    Error::Credential("Wrong passphrase or modified credential file".into())
+                })?,
+        );
+        std::str::from_utf8(&plaintext)
+            .map(Secret::new)
+            .map_err(|_| Error::Credential("The stored password isn't UTF-8".into()))
+    }
+
+    impl CredentialProvider for EncryptedFileCredential {
+        fn password<'a>(&'a self, _user: &'a str) -> CredentialFuture<'a> {
+            Box::pin(async move {
+                let content = tokio::fs::read_to_string(&self.path).await?;
+                let passphrase = self.passphrase.clone();
+                // the key derivation takes a while, keep it off the async workers
+                tokio::task::spawn_blocking(move || decrypt(&passphrase, &content))
+                    .await
+                    .map_err(|e| Error::Credential(format!("Decrypt password failed: {}", e)))?
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CallbackCredential, CredentialProvider, Secret};
+    #[cfg(feature = "credential-file")]
+    use crate::error::Error;
+
+    #[test]
+    fn redact_secret() {
+        let secret = Secret::new("password");
+        assert_eq!(format!("{:?}", secret), r#"Secret("***")"#);
+        assert_eq!(secret.expose(), "password");
+    }
+
+    #[tokio::test]
+    async fn callback_credential() {
+        let provider = CallbackCredential::new(|user: String| async move {
+            Ok(Secret::new(format!("{}-password", user)))
+        });
+        let password = provider.password("user").await.unwrap();
+        assert_eq!(password.expose(), "user-password");
+    }
+
+    #[cfg(feature = "credential-file")]
+    #[tokio::test]
+    async fn encrypted_file_credential() {
+        use base64::{Engine, prelude::BASE64_STANDARD};
+
+        use super::EncryptedFileCredential;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credential");
+        let provider =
+            EncryptedFileCredential::new(&path, &Secret::new("passphrase")).iterations(1_000);
+        provider.store(&Secret::new("password")).unwrap();
+        // the iterations are read from the file
+        let reader = EncryptedFileCredential::new(&path, &Secret::new("passphrase"));
+        assert_eq!(reader.password("user").await.unwrap().expose(), "password");
+
+        let wrong = EncryptedFileCredential::new(&path, &Secret::new("wrong"));
+        assert!(wrong.password("user").await.is_err());
+
+        let mut content = BASE64_STANDARD
+            .decode(std::fs::read_to_string(&path).unwrap())
+            .unwrap();
+        *content.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, BASE64_STANDARD.encode(&content)).unwrap();
+        assert!(reader.password("user").await.is_err());
+
+        // rejected before deriving the key
+        content[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+        std::fs::write(&path, BASE64_STANDARD.encode(&content)).unwrap();
+        assert!(matches!(
+            reader.password("user").await,
+            Err(Error::Credential(message)) if message.contains("iterations")
+        ));
+    }
+}
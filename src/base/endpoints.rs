@@ -0,0 +1,91 @@
+use std::sync::LazyLock;
+
+use reqwest::Url;
+
+use crate::{
+    error::{Error, Result},
+    internals::fields::{
+        ROOT_ICCARD, ROOT_JWCAS, ROOT_JWQYWX, ROOT_LAB, ROOT_SSO, ROOT_VPN, ROOT_YWTB,
+        WECHAT_APP_API, WECHAT_APP_API_FALLBACK,
+    },
+};
+
+pub static DEFAULT_ENDPOINTS: LazyLock<Endpoints> = LazyLock::new(Endpoints::default);
+
+/// Base urls of the campus systems, without trailing `/`.
+///
+/// Every application resolves its root from [`crate::base::client::Client::endpoints`],
+/// so you can point the crate at a mock server or a mirror.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub sso: String,
+    pub vpn: String,
+    pub ywtb: String,
+    /// The jwqywx api, served on `:8180`
+    pub jwqywx_api: String,
+    /// Used if [`Endpoints::jwqywx_api`] can't be connected.
+    pub jwqywx_api_fallback: Option<String>,
+    /// The jwqywx web page, sent as `Referer` and `Origin`
+    pub jwqywx: String,
+    pub jwcas: String,
+    pub iccard: String,
+    pub lab: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            sso: ROOT_SSO.into(),
+            vpn: ROOT_VPN.into(),
+            ywtb: ROOT_YWTB.into(),
+            jwqywx_api: WECHAT_APP_API.into(),
+            jwqywx_api_fallback: Some(WECHAT_APP_API_FALLBACK.into()),
+            jwqywx: ROOT_JWQYWX.into(),
+            jwcas: ROOT_JWCAS.into(),
+            iccard: ROOT_ICCARD.into(),
+            lab: ROOT_LAB.into(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Serve every system from one root, useful for a local mock server.
+    pub fn single_host(root: impl Into<String>) -> Self {
+        let root = root.into().trim_end_matches('/').to_string();
+        Self {
+            sso: root.clone(),
+            vpn: root.clone(),
+            ywtb: root.clone(),
+            jwqywx_api: root.clone(),
+            jwqywx_api_fallback: None,
+            jwqywx: root.clone(),
+            jwcas: root.clone(),
+            iccard: root.clone(),
+            lab: root,
+        }
+    }
+
+    pub fn sso_login(&self) -> String {
+        format!("{}/sso/login", self.sso)
+    }
+
+    pub fn sso_logout(&self) -> String {
+        format!("{}/sso/logout", self.sso)
+    }
+
+    pub fn vpn_logout(&self) -> String {
+        format!("{}/enlink/sso/logout", self.vpn)
+    }
+
+    pub fn sso_url(&self) -> Result<Url> {
+        parse_url(&self.sso)
+    }
+
+    pub fn vpn_url(&self) -> Result<Url> {
+        parse_url(&self.vpn)
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url> {
+    Url::parse(url).map_err(|e| Error::InvalidInput(format!("Invalid endpoint `{}`: {}", url, e)))
+}
@@ -1,2 +1,8 @@
 pub mod app;
+pub mod captcha;
 pub mod client;
+pub mod credentials;
+pub mod endpoints;
+pub mod properties;
+pub mod retry;
+pub mod transport;
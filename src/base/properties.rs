@@ -0,0 +1,147 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// A value which is kept when [`Properties`] is serialized.
+pub trait PersistentProperty: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Stable name of the value in the serialized store.
+    const KEY: &'static str;
+}
+
+/// Typed storage shared by a client and its applications.
+///
+/// Values inserted by [`Properties::insert`] are keyed by their type and live in memory only,
+/// values inserted by [`Properties::insert_persistent`] are keyed by [`PersistentProperty::KEY`]
+/// and serialized together with the store.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Properties {
+    #[serde(skip)]
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    #[serde(flatten)]
+    persistent: HashMap<String, Value>,
+}
+
+impl Debug for Properties {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Properties")
+            .field("values", &self.values.len())
+            .field("persistent", &self.persistent.keys())
+            .finish()
+    }
+}
+
+impl Properties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the previous value of the same type.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok().map(|previous| *previous))
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn get_mut<T: Any + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.values.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|value| *value)
+    }
+
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn insert_persistent<T: PersistentProperty>(&mut self, value: &T) -> Result<()> {
+        let value = serde_json::to_value(value)
+            .map_err(|e| Error::parse_with("properties", "Serialize property failed", e))?;
+        self.persistent.insert(T::KEY.to_string(), value);
+        Ok(())
+    }
+
+    /// Return `Err` if the stored value doesn't match `T`.
+    pub fn get_persistent<T: PersistentProperty>(&self) -> Result<Option<T>> {
+        self.persistent
+            .get(T::KEY)
+            .map(|value| {
+                T::deserialize(value)
+                    .map_err(|e| Error::parse_with("properties", "Deserialize property failed", e))
+            })
+            .transpose()
+    }
+
+    pub fn remove_persistent<T: PersistentProperty>(&mut self) -> bool {
+        self.persistent.remove(T::KEY).is_some()
+    }
+
+    pub fn contains_persistent<T: PersistentProperty>(&self) -> bool {
+        self.persistent.contains_key(T::KEY)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use super::{PersistentProperty, Properties};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Token(String);
+
+    impl PersistentProperty for Token {
+        const KEY: &'static str = "token";
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Other(i32);
+
+    impl PersistentProperty for Other {
+        const KEY: &'static str = "token";
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut properties = Properties::new();
+        assert_eq!(properties.insert(1i32), None);
+        assert_eq!(properties.insert(2i32), Some(1));
+        assert_eq!(properties.get::<i32>(), Some(&2));
+        assert_eq!(properties.get::<u32>(), None);
+        assert_eq!(properties.remove::<i32>(), Some(2));
+        assert!(!properties.contains::<i32>());
+    }
+
+    #[test]
+    fn persistent_values() {
+        let mut properties = Properties::new();
+        properties.insert(1i32);
+        properties
+            .insert_persistent(&Token("bearer".into()))
+            .unwrap();
+
+        let json = serde_json::to_string(&properties).unwrap();
+        let restored: Properties = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.get_persistent::<Token>().unwrap(),
+            Some(Token("bearer".into()))
+        );
+        assert!(restored.get_persistent::<Other>().is_err());
+        assert_eq!(restored.get::<i32>(), None);
+    }
+}
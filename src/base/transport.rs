@@ -0,0 +1,23 @@
+use std::{future::Future, pin::Pin};
+
+use reqwest::{Request, Response};
+
+use crate::error::Result;
+
+pub type TransportFuture = Pin<Box<dyn Future<Output = Result<Response>> + Send>>;
+
+/// Sends the requests built by the applications.
+///
+/// Requests are still built with [`crate::base::client::Client::reqwest_client`],
+/// only sending goes through [`crate::base::client::Client::transport`],
+/// so tests can serve canned responses, see [`crate::impls::transport::MemoryTransport`].
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: Request) -> TransportFuture;
+}
+
+impl Transport for reqwest::Client {
+    fn execute(&self, request: Request) -> TransportFuture {
+        let client = self.clone();
+        Box::pin(async move { Ok(client.execute(request).await?) })
+    }
+}
@@ -0,0 +1,151 @@
+use reqwest::{StatusCode, Url};
+
+use crate::impls::login::sso_type::SSOVerification;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error returned by every API of this crate.
+///
+/// Match on the variant to decide whether to retry, re-login or alert,
+/// the underlying cause is always available via [`std::error::Error::source`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request could not be sent or the response could not be received.
+    #[error("network request failed")]
+    Network(#[source] reqwest::Error),
+    /// The server rejected the account, e.g. wrong password.
+    #[error("authentication failed: {0}")]
+    AuthenticationFailed(String),
+    /// The SSO locked or froze the account, usually after too many failed logins.
+    #[error("account locked: {0}")]
+    AccountLocked(String),
+    /// The SSO wants a captcha to be solved before accepting the password.
+    #[error("captcha required: {0}")]
+    CaptchaRequired(String),
+    /// The password is correct but has to be changed before logging in.
+    #[error("password expired: {0}")]
+    PasswordExpired(String),
+    /// The SSO wants a code sent to the user, continue the login with the challenge.
+    #[error("verification required")]
+    VerificationRequired(Box<SSOVerification>),
+    /// The session was valid before but the server does not accept it anymore.
+    #[error("session expired: {0}")]
+    SessionExpired(String),
+    /// The operation needs a login which has not been performed on this client.
+    #[error("not logged in")]
+    NotLoggedIn,
+    /// The server answered with a status code the crate can't handle.
+    #[error("unexpected upstream status: {0}")]
+    UpstreamStatus(StatusCode),
+    /// A redirect chain was longer than [`crate::base::client::Client::max_redirects`],
+    /// with the urls visited.
+    #[error("too many redirects after {}", .0.last().map_or("", |url| url.as_str()))]
+    TooManyRedirects(Vec<Url>),
+    /// A url redirected to the same location twice, with the urls visited.
+    #[error("redirect loop at {}", .0.last().map_or("", |url| url.as_str()))]
+    RedirectLoop(Vec<Url>),
+    /// The page or payload doesn't look like what we expect, usually the school changed it.
+    #[error("failed to parse {page}: {detail}")]
+    ParseError {
+        page: &'static str,
+        detail: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+    /// The password couldn't be read from the
+    /// [`crate::base::credentials::CredentialProvider`].
+    #[error("credential unavailable: {0}")]
+    Credential(String),
+    /// The input given by the caller can't be used.
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    /// Reading or writing a local file failed, e.g. a session or credential file.
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    pub fn parse(page: &'static str, detail: impl Into<String>) -> Self {
+        Self::ParseError {
+            page,
+            detail: detail.into(),
+            source: None,
+        }
+    }
+
+    pub fn parse_with(
+        page: &'static str,
+        detail: impl Into<String>,
+        source: impl Into<BoxError>,
+    ) -> Self {
+        Self::ParseError {
+            page,
+            detail: detail.into(),
+            source: Some(source.into()),
+        }
+    }
+}
+
+impl Error {
+    /// A copy for the other callers of a coalesced operation,
+    /// `None` for [`Error::Network`] which can't be copied.
+    pub(crate) fn share(&self) -> Option<Self> {
+        Some(match self {
+            Self::Network(_) => return None,
+            Self::AuthenticationFailed(message) => Self::AuthenticationFailed(message.clone()),
+            Self::AccountLocked(message) => Self::AccountLocked(message.clone()),
+            Self::CaptchaRequired(message) => Self::CaptchaRequired(message.clone()),
+            Self::PasswordExpired(message) => Self::PasswordExpired(message.clone()),
+            Self::VerificationRequired(verification) => {
+                Self::VerificationRequired(verification.clone())
+            }
+            Self::SessionExpired(message) => Self::SessionExpired(message.clone()),
+            Self::NotLoggedIn => Self::NotLoggedIn,
+            Self::UpstreamStatus(status) => Self::UpstreamStatus(*status),
+            Self::TooManyRedirects(urls) => Self::TooManyRedirects(urls.clone()),
+            Self::RedirectLoop(urls) => Self::RedirectLoop(urls.clone()),
+            Self::ParseError {
+                page,
+                detail,
+                source,
+            } => Self::ParseError {
+                page,
+                detail: detail.clone(),
+                source: source.as_ref().map(|source| source.to_string().into()),
+            },
+            Self::Credential(message) => Self::Credential(message.clone()),
+            Self::InvalidInput(message) => Self::InvalidInput(message.clone()),
+            Self::Io(error) => Self::Io(std::io::Error::new(error.kind(), error.to_string())),
+        })
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        if value.is_decode() {
+            Self::parse_with("response", "Decode response body failed", value)
+        } else {
+            Self::Network(value)
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::parse_with("json", "Deserialize json failed", value)
+    }
+}
+
+/// Turn a missing value into [`Error::ParseError`]
+pub(crate) trait OptionExt<T> {
+    fn ok_or_parse(self, page: &'static str, detail: impl Into<String>) -> Result<T>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    fn ok_or_parse(self, page: &'static str, detail: impl Into<String>) -> Result<T> {
+        self.ok_or_else(|| Error::parse(page, detail))
+    }
+}
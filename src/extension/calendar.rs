@@ -1,13 +1,10 @@
 use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use icalendar::{Alarm, Calendar, Component, Event, EventLike, Trigger};
 use serde::{Deserialize, Serialize};
-use std::{
-    collections::HashMap, fs::read_to_string, future::Future, io::ErrorKind, path::Path,
-    sync::LazyLock,
-};
+use std::{collections::HashMap, fs::read_to_string, future::Future, path::Path, sync::LazyLock};
 use uuid::Uuid;
 
-use anyhow::{Result, bail};
+use crate::error::{Error, Result};
 
 pub static EVENT_PROP: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
     let mut map: HashMap<&str, &str> = HashMap::new();
@@ -40,6 +37,7 @@ impl Schedule {
         serde_json::from_str(&read_to_string(path).unwrap()).unwrap()
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(data: &str) -> Self {
         serde_json::from_str(data).unwrap()
     }
@@ -220,7 +218,6 @@ pub trait CalendarParser {
     /// The Matrix's column is indexed 0~6
     ///
     /// Each Vec<String> is in order.
-
     fn get_classinfo_week_matrix(&self) -> impl Future<Output = Result<Vec<Vec<RawCourse>>>>;
 }
 
@@ -247,29 +244,29 @@ impl<P: CalendarParser> ApplicationCalendarExt for P {
         });
 
         for info in classlist.iter() {
-            let start_time =
-                schedule.classtime[info.classtime.first().ok_or_else(|| {
-                    tokio::io::Error::new(ErrorKind::InvalidData, "No First data")
-                })? - 1]
-                    .clone()
-                    .start_time;
-            let end_time =
-                schedule.classtime[info.classtime.last().ok_or_else(|| {
-                    tokio::io::Error::new(ErrorKind::InvalidData, "No Last data")
-                })? - 1]
-                    .clone()
-                    .end_time;
+            let first = info
+                .classtime
+                .first()
+                .ok_or_else(|| Error::parse("course", "No First data"))?;
+            let last = info
+                .classtime
+                .last()
+                .ok_or_else(|| Error::parse("course", "No Last data"))?;
+            let start_time = schedule.classtime[first - 1].clone().start_time;
+            let end_time = schedule.classtime[last - 1].clone().end_time;
             let create_time = Utc::now();
             for day in info.daylist.iter() {
                 let uid = format!("{}@gmail.com", Uuid::new_v4());
                 let start = NaiveDateTime::parse_from_str(
                     format!("{}{}", day, start_time).as_str(),
                     "%Y%m%d%H%M",
-                )?;
+                )
+                .map_err(|e| Error::parse_with("schedule", "Invalid start time", e))?;
                 let end = NaiveDateTime::parse_from_str(
                     format!("{}{}", day, end_time).as_str(),
                     "%Y%m%d%H%M",
-                )?;
+                )
+                .map_err(|e| Error::parse_with("schedule", "Invalid end time", e))?;
 
                 let mut event = Event::new();
 
@@ -350,7 +347,7 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> Result<Vec<ParsedCo
             if let Some(value) = v.get(i) {
                 column.push(value.clone())
             } else {
-                bail!("Parse Classinfo error");
+                return Err(Error::parse("week matrix", "Parse Classinfo error"));
             }
         }
         column_matrix.push(column);
@@ -422,7 +419,9 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> Result<Vec<ParsedCo
                     week = chucks[3].clone();
                 }
 
-                if !course_info.contains_key(&id) {
+                if let Some(info) = course_info.get_mut(&id) {
+                    info.add_classtime(time + 1);
+                } else {
                     let info = ParsedCourse::new(
                         name,
                         match oe.as_str() {
@@ -439,20 +438,14 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> Result<Vec<ParsedCo
                         place,
                         teachers
                             .get(index)
-                            .map(|e| e.clone())
+                            .cloned()
                             .unwrap_or("未知教师".to_owned()),
                     );
                     course_info.insert(id, info);
-                } else {
-                    course_info.get_mut(&id).unwrap().add_classtime(time + 1);
                 }
             }
         }
     }
 
-    Ok(course_info
-        .values()
-        .into_iter()
-        .map(|e| e.clone())
-        .collect())
+    Ok(course_info.values().cloned().collect())
 }
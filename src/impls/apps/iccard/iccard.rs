@@ -4,13 +4,13 @@ use tokio::join;
 
 use crate::{
     base::{app::Application, client::Client},
+    error::Result,
     impls::apps::iccard::{
         iccard_constants::PRESET_DORMBUILDINGS,
         iccard_type::{DormArea, DormBuilding, DormBuildingsData, DormRoomElectricityBillData},
     },
-    internals::fields::DEFAULT_HEADERS,
+    internals::request::RequestBuilderExt,
 };
-use anyhow::Result;
 
 pub struct ICCardApplication<C, S> {
     pub client: C,
@@ -18,11 +18,11 @@ pub struct ICCardApplication<C, S> {
 }
 
 impl<C: Client + Clone> Application<C> for ICCardApplication<C, String> {
-    async fn from_client(client: &C) -> Self {
-        Self {
+    async fn from_client(client: &C) -> Result<Self> {
+        Ok(Self {
             client: client.clone(),
-            root: "http://wxxy.cczu.edu.cn".to_owned(),
-        }
+            root: client.endpoints().iccard.clone(),
+        })
     }
 }
 
@@ -44,7 +44,7 @@ impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
             .client
             .reqwest_client()
             .post(url)
-            .headers(DEFAULT_HEADERS.clone())
+            .headers(self.client.headers())
             .query(&serde_json::json!({
                 "aid": &areaid,
                 "account": self.client.account().user,
@@ -65,7 +65,7 @@ impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
                     "roomid": room.into()
                 }).to_string(),
             }))
-            .send()
+            .send_idempotent_with(&self.client)
             .await?;
         Ok(response.json().await?)
     }
@@ -86,13 +86,13 @@ impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
             .client
             .reqwest_client()
             .post(url)
-            .headers(DEFAULT_HEADERS.clone())
+            .headers(self.client.headers())
             .query(&serde_json::json!({
                 "aid": &id,
                 "area": area.to_string(),
                 "account": self.client.account().user,
             }))
-            .send()
+            .send_idempotent_with(&self.client)
             .await?;
         Ok(response.json().await?)
     }
@@ -124,7 +124,7 @@ mod ptest {
     async fn test() {
         tokio::spawn(async {
             let client = DefaultClient::iccard("1");
-            let app = client.visit::<ICCardApplication<_, _>>().await;
+            let app = client.visit::<ICCardApplication<_, _>>().await.unwrap();
 
             println!("{:?}", app.list_all_preset_buildings().await.unwrap());
         });
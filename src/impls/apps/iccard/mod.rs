@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod iccard;
 pub mod iccard_constants;
 pub mod iccard_type;
@@ -1,13 +1,16 @@
 use std::fmt::Display;
 
+use reqwest::RequestBuilder;
 use scraper::{ElementRef, Html, Selector};
 
 use crate::base::client::Client;
-use crate::impls::login::sso::parse_hidden_values;
+use crate::error::{Error, OptionExt, Result};
+use crate::impls::login::sso::{SSOUniversalLogin, parse_hidden_values};
 use crate::impls::services::sso_redirect::SSORedirect;
-use crate::internals::recursion::recursion_redirect_handle;
+use crate::internals::redirect::follow_get;
+use crate::internals::request::RequestBuilderExt;
+use crate::internals::session::read_sso_page;
 use crate::{base::app::Application, impls::apps::sso::jwcas_type::TechPlanData};
-use anyhow::{Context, Result};
 
 use super::jwcas_type::GradeData;
 
@@ -18,18 +21,22 @@ pub struct JwcasApplication<C> {
 }
 
 impl<C: Client + Clone> Application<C> for JwcasApplication<C> {
-    async fn from_client(client: &C) -> Self {
-        Self {
+    /// Return [`Error::NotLoggedIn`] if the SSO isn't logged in,
+    /// the root depends on how the SSO was reached.
+    async fn from_client(client: &C) -> Result<Self> {
+        Ok(Self {
             client: client.clone(),
-            root: client.sso_redirect("http://219.230.159.132").await,
-        }
+            root: client.sso_redirect(&client.endpoints().jwcas).await?,
+        })
     }
 }
 
 impl<C: Client + Clone + Send> JwcasApplication<C> {
     /// will call login after [`Self::from_client`]
+    ///
+    /// Return [`Error::NotLoggedIn`] if the SSO isn't logged in.
     pub async fn from_client_login(client: C) -> Result<Self> {
-        let app = Self::from_client(&client).await;
+        let app = Self::from_client(&client).await?;
         app.login().await?;
         Ok(app)
     }
@@ -37,7 +44,7 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
     /// Visit this Url will login in too.
     pub async fn login(&self) -> Result<()> {
         let api = format!("{}/web_cas/web_cas_login_jwgl.aspx", self.root);
-        recursion_redirect_handle(self.client.clone(), &api).await?;
+        follow_get(&self.client, &api).await?;
         Ok(())
     }
 
@@ -61,25 +68,27 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
             ("__EVENTARGUMENT", "CmdWh$0"),
             (
                 "__VIEWSTATE",
-                hiddens.get("__VIEWSTATE").context("Get ViewState Failed")?,
+                hiddens
+                    .get("__VIEWSTATE")
+                    .ok_or_parse("jwcas techplan", "Get ViewState Failed")?,
             ),
             (
                 "__VIEWSTATEGENERATOR",
                 hiddens
                     .get("__VIEWSTATEGENERATOR")
-                    .context("Get ViewStateGenerator Failed")?,
+                    .ok_or_parse("jwcas techplan", "Get ViewStateGenerator Failed")?,
             ),
             (
                 "__VIEWSTATEENCRYPTED",
                 hiddens
                     .get("__VIEWSTATEENCRYPTED")
-                    .context("Get ViewStateCrypted Failed")?,
+                    .ok_or_parse("jwcas techplan", "Get ViewStateCrypted Failed")?,
             ),
             (
                 "Txtcxxq",
                 dom.select(&txtcxxq_selector)
                     .next()
-                    .context("Get Txtcxxq Failed")?
+                    .ok_or_parse("jwcas techplan", "Get Txtcxxq Failed")?
                     .value()
                     .attr("value")
                     .unwrap_or(""),
@@ -88,10 +97,10 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
                 "DDnj",
                 dom.select(&ddnj_selector)
                     .next()
-                    .context("Get DDnj Failed")?
+                    .ok_or_parse("jwcas techplan", "Get DDnj Failed")?
                     .child_elements()
                     .next()
-                    .context("Get DDnj Child Failed")?
+                    .ok_or_parse("jwcas techplan", "Get DDnj Child Failed")?
                     .value()
                     .attr("value")
                     .unwrap_or(""),
@@ -102,29 +111,38 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
         self.post_html("/web_jxjh/jxjh_cx.aspx", &form).await
     }
 
+    /// Return [`Error::SessionExpired`] if the SSO session is gone,
+    /// see [`Client::auto_relogin`].
     pub async fn get_html(&self, service: impl Display) -> Result<String> {
         let api = format!("{}{}", self.root, service);
-        Ok(self
-            .client
-            .reqwest_client()
-            .get(api)
-            .send()
-            .await?
-            .text()
-            .await?)
+        self.with_session(|| self.client.reqwest_client().get(&api))
+            .await
     }
 
+    /// Return [`Error::SessionExpired`] if the SSO session is gone,
+    /// see [`Client::auto_relogin`].
     pub async fn post_html(&self, service: impl Display, form: &[(&str, &str)]) -> Result<String> {
         let api = format!("{}{}", self.root, service);
-        Ok(self
-            .client
-            .reqwest_client()
-            .post(api)
-            .form(form)
-            .send()
-            .await?
-            .text()
-            .await?)
+        self.with_session(|| self.client.reqwest_client().post(&api).form(form))
+            .await
+    }
+
+    async fn with_session(&self, request: impl Fn() -> RequestBuilder) -> Result<String> {
+        let response = request().send_with(&self.client).await?;
+        match read_sso_page(response).await {
+            Err(Error::SessionExpired(reason)) if self.client.auto_relogin() => {
+                self.relogin().await.map_err(|e| {
+                    Error::SessionExpired(format!("{}, re-login failed: {}", reason, e))
+                })?;
+                read_sso_page(request().send_with(&self.client).await?).await
+            }
+            result => result,
+        }
+    }
+
+    async fn relogin(&self) -> Result<()> {
+        self.client.sso_universal_login().await?;
+        self.login().await
     }
 
     pub async fn get_techplans(&self) -> Result<Vec<TechPlanData>> {
@@ -135,7 +153,7 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
         Ok(dom
             .select(&tb_up)
             .next()
-            .context("Select Grade Table Failed")?
+            .ok_or_parse("jwcas techplan", "Select TechPlan Table Failed")?
             .select(&selector)
             .map(|e| {
                 let childs: Vec<ElementRef> = e.child_elements().collect();
@@ -165,7 +183,7 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
         Ok(dom
             .select(&tb_up)
             .next()
-            .context("Select Grade Table Failed")?
+            .ok_or_parse("jwcas gradelist", "Select Grade Table Failed")?
             .select(&selector)
             .map(|e| {
                 let childs: Vec<ElementRef> = e.child_elements().collect();
@@ -181,7 +199,7 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
 
 fn extract_string(element: Option<&ElementRef>) -> String {
     if let Some(element) = element {
-        element.text().next().unwrap().to_string()
+        element.text().next().unwrap_or_default().to_string()
     } else {
         String::new()
     }
@@ -191,10 +209,10 @@ fn extract_string(element: Option<&ElementRef>) -> String {
 pub mod calendar {
     use std::collections::HashMap;
 
-    use anyhow::{Context, Result};
     use scraper::{Html, Selector};
 
     use crate::base::client::Client;
+    use crate::error::{OptionExt, Result};
     use crate::extension::calendar::{CalendarParser, RawCourse};
 
     use super::JwcasApplication;
@@ -215,7 +233,7 @@ pub mod calendar {
             let mut teachers = HashMap::new();
             doc.select(&tb_up_rowseletor)
                 .next()
-                .context("Select Teacher Failed")?
+                .ok_or_parse("jwcas classlist", "Select Teacher Failed")?
                 .select(&tb_dg1_itemseletor)
                 .for_each(|e| {
                     let items: Vec<String> = e
@@ -237,7 +255,7 @@ pub mod calendar {
             Ok(doc
                 .select(&tb_dn_seletor)
                 .next()
-                .context("Select Course Failed")?
+                .ok_or_parse("jwcas classlist", "Select Course Failed")?
                 .select(&tb_dg1_itemseletor)
                 .map(|e| {
                     let mut items: Vec<String> = e
@@ -281,3 +299,70 @@ pub mod calendar {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use reqwest::Method;
+
+    use super::JwcasApplication;
+    use crate::{
+        base::app::AppVisitor,
+        error::Error,
+        impls::{
+            client::DefaultClient,
+            transport::{MemoryTransport, MockResponse},
+        },
+    };
+
+    const GRADES: &str = "http://jwcas.test/web_cjgl/cx_cj_jxjhcj_xh.aspx";
+
+    fn app(transport: &MemoryTransport) -> JwcasApplication<DefaultClient> {
+        JwcasApplication {
+            client: DefaultClient::builder()
+                .transport(transport.clone())
+                .build()
+                .unwrap(),
+            root: "http://jwcas.test".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn parse_grades() {
+        let row = ["", "", "", "", "", "高等数学", "", "", "4.0", "92"]
+            .map(|cell| format!("<td>{}</td>", cell))
+            .concat();
+        let html = format!(
+            r#"<table id="GVkbk"><tr class="dg1-head"></tr><tr class="dg1-item">{}</tr></table>"#,
+            row
+        );
+        let transport = MemoryTransport::new().route(Method::GET, GRADES, MockResponse::ok(html));
+
+        let grades = app(&transport).get_gradeinfo_vec().await.unwrap();
+        assert_eq!(grades.len(), 1);
+        assert_eq!(grades[0].name, "高等数学");
+        assert_eq!(grades[0].point, "4.0");
+        assert_eq!(grades[0].grade, "92");
+    }
+
+    #[tokio::test]
+    async fn detect_expired_session() {
+        let transport = MemoryTransport::new().route(
+            Method::GET,
+            GRADES,
+            MockResponse::redirect("http://sso.test/sso/login?service=http://jwcas.test/"),
+        );
+
+        let result = app(&transport).get_gradeinfo_vec().await;
+        assert!(matches!(result, Err(Error::SessionExpired(_))));
+    }
+
+    #[tokio::test]
+    async fn visit_before_login() {
+        let client = DefaultClient::builder()
+            .transport(MemoryTransport::new())
+            .build()
+            .unwrap();
+        let app = client.visit::<JwcasApplication<_>>().await;
+        assert!(matches!(app, Err(Error::NotLoggedIn)));
+    }
+}
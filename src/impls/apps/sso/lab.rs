@@ -2,22 +2,22 @@ use std::collections::HashMap;
 
 use crate::{
     base::{app::Application, client::Client},
+    error::Result,
     impls::login::sso::SSOUniversalLogin,
-    internals::fields::DEFAULT_HEADERS,
+    internals::request::RequestBuilderExt,
 };
-use anyhow::Result;
-
-static LABAPP_ROOT: &'static str = "https://sysaqgl.cczu.edu.cn";
 
 pub struct LabApplication<C> {
     client: C,
+    root: String,
 }
 
 impl<C: Client + Clone> Application<C> for LabApplication<C> {
-    async fn from_client(client: &C) -> Self {
-        Self {
+    async fn from_client(client: &C) -> Result<Self> {
+        Ok(Self {
             client: client.clone(),
-        }
+            root: client.endpoints().lab.clone(),
+        })
     }
 }
 
@@ -25,14 +25,14 @@ impl<C: Client + Clone + Send + Sync> LabApplication<C> {
     /// Support LAN/WAN
     pub async fn exam_login(&self) -> Result<()> {
         self.client
-            .sso_service_login(format!("{LABAPP_ROOT}/labexam/examIDSLogin.php"))
+            .sso_service_login(format!("{}/labexam/examIDSLogin.php", self.root))
             .await?;
 
         Ok(())
     }
 
     pub async fn exam_increase_thirty_secs(&self) -> Result<LabExamStudyInfo> {
-        let api = format!("{LABAPP_ROOT}/labexam/exam_xuexi_online.php");
+        let api = format!("{}/labexam/exam_xuexi_online.php", self.root);
         let mut params = HashMap::new();
         params.insert("cmd", "xuexi_online");
 
@@ -40,9 +40,9 @@ impl<C: Client + Clone + Send + Sync> LabApplication<C> {
             .client
             .reqwest_client()
             .post(api)
-            .headers(DEFAULT_HEADERS.clone())
+            .headers(self.client.headers())
             .form(&params)
-            .send()
+            .send_with(&self.client)
             .await?
             .json()
             .await?)
@@ -1,116 +1,257 @@
-use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, ORIGIN, REFERER};
+use reqwest::{
+    Response, StatusCode,
+    header::{AUTHORIZATION, HeaderMap, HeaderValue, ORIGIN, REFERER},
+};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{Value, json};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 
 use crate::{
     base::{
         app::{Application, CachedApplication},
-        client::{Client, Property},
+        client::Client,
+        properties::PersistentProperty,
     },
+    error::{Error, Result},
     impls::apps::wechat::jwqywx_type::EvaluatableClass,
-    internals::fields::{DEFAULT_HEADERS, WECHAT_APP_API},
+    internals::{request::RequestBuilderExt, single_flight::client_flight},
 };
-use anyhow::{Context, Ok, Result};
 
 use super::jwqywx_type::{CourseGrade, Exam, LoginUserData, Message, StudentPoint, Term};
 
+/// Borrows the password, so no copy outlives the [`crate::base::credentials::Secret`].
+#[derive(Serialize)]
+struct LoginRequest<'a> {
+    userid: String,
+    userpwd: &'a str,
+}
+
+/// The token of a client, shared by every [`JwqywxApplication`] built from it.
+struct JwqywxSession {
+    /// Switch to [`crate::base::endpoints::Endpoints::jwqywx_api_fallback`] when the host is down
+    root: RwLock<String>,
+    headers: RwLock<HeaderMap>,
+    authorizationid: RwLock<Option<String>>,
+}
+
+impl JwqywxSession {
+    fn new(client: &impl Client) -> Self {
+        Self {
+            root: RwLock::new(client.endpoints().jwqywx_api.clone()),
+            headers: RwLock::new(web_headers(client)),
+            authorizationid: RwLock::new(None),
+        }
+    }
+}
+
+/// The [`JwqywxSession`] kept in [`crate::base::properties::Properties`].
+async fn client_session(client: &impl Client) -> Arc<JwqywxSession> {
+    let properties = client.properties();
+    if let Some(session) = properties.read().await.get::<Arc<JwqywxSession>>() {
+        return session.clone();
+    }
+    let mut properties = properties.write().await;
+    match properties.get::<Arc<JwqywxSession>>() {
+        Some(session) => session.clone(),
+        None => {
+            let session = Arc::new(JwqywxSession::new(client));
+            properties.insert(session.clone());
+            session
+        }
+    }
+}
+
+/// Applications of the same client share the token and one login.
+#[derive(Clone)]
 pub struct JwqywxApplication<C> {
     client: C,
-    headers: Arc<RwLock<HeaderMap>>,
-    authorizationid: Arc<RwLock<Option<String>>>,
+    session: Arc<JwqywxSession>,
 }
 
 impl<C: Client + Clone> Application<C> for JwqywxApplication<C> {
-    async fn from_client(client: &C) -> Self {
-        let mut header = DEFAULT_HEADERS.clone();
-        header.insert(
-            REFERER,
-            HeaderValue::from_static("http://jwqywx.cczu.edu.cn/"),
-        );
-        header.insert(
-            ORIGIN,
-            HeaderValue::from_static("http://jwqywx.cczu.edu.cn"),
-        );
-        Self {
+    async fn from_client(client: &C) -> Result<Self> {
+        Ok(Self {
             client: client.clone(),
-            headers: Arc::new(RwLock::new(header)),
-            authorizationid: Arc::new(RwLock::new(None)),
-        }
+            session: client_session(client).await,
+        })
     }
 }
 
+fn web_headers(client: &impl Client) -> HeaderMap {
+    let mut header = client.headers();
+    let web = &client.endpoints().jwqywx;
+    if let Ok(referer) = HeaderValue::from_str(&format!("{}/", web)) {
+        header.insert(REFERER, referer);
+    }
+    if let Ok(origin) = HeaderValue::from_str(web) {
+        header.insert(ORIGIN, origin);
+    }
+    header
+}
+
 impl<C: Client> JwqywxApplication<C> {
+    async fn api(&self, path: &str) -> String {
+        format!("{}{}", self.session.root.read().await, path)
+    }
+
+    /// Fallback to [`crate::base::endpoints::Endpoints::jwqywx_api_fallback`]
+    /// if the jwqywx host can't be connected.
+    ///
+    /// Concurrent calls on the applications of the same client share one login.
     pub async fn login(&self) -> Result<Message<LoginUserData>> {
-        let account = self.client.account();
+        client_flight::<Message<LoginUserData>>(&self.client)
+            .await
+            .run(|| self.login_with_fallback())
+            .await
+    }
+
+    async fn login_with_fallback(&self) -> Result<Message<LoginUserData>> {
+        match self.login_request().await {
+            Err(Error::Network(error)) if error.is_connect() || error.is_timeout() => {
+                let fallback = self.client.endpoints().jwqywx_api_fallback.clone();
+                match fallback {
+                    Some(fallback) if *self.session.root.read().await != fallback => {
+                        *self.session.root.write().await = fallback;
+                        self.login_request().await
+                    }
+                    _ => Err(Error::Network(error)),
+                }
+            }
+            result => result,
+        }
+    }
+
+    async fn login_request(&self) -> Result<Message<LoginUserData>> {
+        let password = self.client.password().await?;
         let response = self
             .client
             .reqwest_client()
-            .post(format!("{}/api/login", WECHAT_APP_API))
-            .headers(DEFAULT_HEADERS.clone())
-            .header("Referer", "http://jwqywx.cczu.edu.cn/")
-            .header("Origin", "http://jwqywx.cczu.edu.cn")
-            .json(&json!({
-                "userid":account.user,
-                "userpwd":account.password,
-            }))
-            .send()
+            .post(self.api("/api/login").await)
+            .headers(web_headers(&self.client))
+            .json(&LoginRequest {
+                userid: self.client.account().user,
+                userpwd: password.expose(),
+            })
+            .send_with(&self.client)
             .await?;
         let text = response.text().await?;
         let message: Message<LoginUserData> = serde_json::from_str(&text)?;
-        self.write_token(format!(
-            "Bearer {}",
-            message.token.clone().context("No token available")?
-        ))
-        .await;
-        self.write_authorizationid(
-            message
-                .message
-                .get(0)
-                .context("Jwqywx Login Failed, No User Data!")?
-                .id
-                .clone(),
-        )
-        .await;
+        let token = message
+            .token
+            .clone()
+            .ok_or_else(|| Error::AuthenticationFailed("No token available".into()))?;
+        let id = message
+            .message
+            .first()
+            .ok_or_else(|| {
+                Error::AuthenticationFailed("Jwqywx Login Failed, No User Data!".into())
+            })?
+            .id
+            .clone();
+        self.write_token(format!("Bearer {}", token)).await?;
+        self.write_authorizationid(id).await;
 
         Ok(message)
     }
 
-    async fn write_token(&self, token: String) {
-        let mut header = DEFAULT_HEADERS.clone();
-        header.insert(AUTHORIZATION, HeaderValue::from_str(&token).unwrap());
-        header.insert(
-            REFERER,
-            HeaderValue::from_static("http://jwqywx.cczu.edu.cn/"),
-        );
+    async fn write_token(&self, token: String) -> Result<()> {
+        let mut header = web_headers(&self.client);
         header.insert(
-            ORIGIN,
-            HeaderValue::from_static("http://jwqywx.cczu.edu.cn"),
+            AUTHORIZATION,
+            HeaderValue::from_str(&token)
+                .map_err(|e| Error::parse_with("jwqywx login", "Invalid token", e))?,
         );
-        *self.headers.write().await = header;
+        *self.session.headers.write().await = header;
+        Ok(())
     }
 
     async fn write_authorizationid(&self, id: String) {
-        let mut authorizationid = self.authorizationid.write().await;
-        *authorizationid = Some(id);
+        *self.session.authorizationid.write().await = Some(id);
     }
 
     async fn get_authorizationid(&self) -> Result<String> {
-        let authorizationid = self.authorizationid.read().await;
-        authorizationid.clone().context("Not logged in")
+        let authorizationid = self.session.authorizationid.read().await;
+        authorizationid.clone().ok_or(Error::NotLoggedIn)
     }
 
-    pub async fn get_grades(&self) -> Result<Message<CourseGrade>> {
-        Ok(self
+    /// Forget the bearer token and the cached application,
+    /// the api has no logout so the token is only dropped locally.
+    pub async fn logout(&self) -> Result<()> {
+        *self.session.headers.write().await = web_headers(&self.client);
+        *self.session.authorizationid.write().await = None;
+        self.client
+            .properties()
+            .write()
+            .await
+            .remove_persistent::<CachedJwqywxApplication>();
+        Ok(())
+    }
+
+    /// Whether the token is still accepted, by asking the credits which are cheap to compute.
+    ///
+    /// Return [`Error::NotLoggedIn`] before [`Self::login`].
+    pub async fn session_alive(&self) -> Result<bool> {
+        let body = json!({
+            "xh":self.get_authorizationid().await?,
+        });
+        let response = self.post_once("/api/cj_xh_xfjd", &body, true).await?;
+        Ok(response.status() != StatusCode::UNAUTHORIZED)
+    }
+
+    /// Whether the application was saved by [`CachedApplication::cache`].
+    pub(crate) async fn is_cached(&self) -> bool {
+        self.client
+            .properties()
+            .read()
+            .await
+            .contains_persistent::<CachedJwqywxApplication>()
+    }
+
+    /// Re-login and replay once if the token is rejected, see [`Client::auto_relogin`].
+    async fn post(&self, path: &str, body: &Value, idempotent: bool) -> Result<Response> {
+        let response = self.post_once(path, body, idempotent).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        if !self.client.auto_relogin() {
+            return Err(Error::SessionExpired("Jwqywx token expired".into()));
+        }
+        self.login().await.map_err(|e| {
+            Error::SessionExpired(format!("Jwqywx token expired, re-login failed: {}", e))
+        })?;
+
+        let response = self.post_once(path, body, idempotent).await?;
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(Error::SessionExpired(
+                "Jwqywx token rejected after re-login".into(),
+            )),
+            _ => Ok(response),
+        }
+    }
+
+    async fn post_once(&self, path: &str, body: &Value, idempotent: bool) -> Result<Response> {
+        let request = self
             .client
             .reqwest_client()
-            .post(format!("{}/api/cj_xh", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
-                "xh":self.get_authorizationid().await?,
-            }))
-            .send()
+            .post(self.api(path).await)
+            .headers(self.session.headers.read().await.clone())
+            .json(body);
+        match idempotent {
+            true => request.send_idempotent_with(&self.client).await,
+            false => request.send_with(&self.client).await,
+        }
+    }
+
+    pub async fn get_grades(&self) -> Result<Message<CourseGrade>> {
+        Ok(self
+            .post(
+                "/api/cj_xh",
+                &json!({
+                    "xh":self.get_authorizationid().await?,
+                }),
+                true,
+            )
             .await?
             .json()
             .await?)
@@ -118,14 +259,13 @@ impl<C: Client> JwqywxApplication<C> {
 
     pub async fn get_credits_and_rank(&self) -> Result<Message<StudentPoint>> {
         Ok(self
-            .client
-            .reqwest_client()
-            .post(format!("{}/api/cj_xh_xfjd", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
-                "xh":self.get_authorizationid().await?,
-            }))
-            .send()
+            .post(
+                "/api/cj_xh_xfjd",
+                &json!({
+                    "xh":self.get_authorizationid().await?,
+                }),
+                true,
+            )
             .await?
             .json()
             .await?)
@@ -135,8 +275,8 @@ impl<C: Client> JwqywxApplication<C> {
         Ok(self
             .client
             .reqwest_client()
-            .get(format!("{}/api/xqall", WECHAT_APP_API))
-            .send()
+            .get(self.api("/api/xqall").await)
+            .send_with(&self.client)
             .await?
             .json()
             .await?)
@@ -145,17 +285,16 @@ impl<C: Client> JwqywxApplication<C> {
     /// Get Term from [`JwqywxApplication::terms`]
     pub async fn get_exams(&self, term: String) -> Result<Message<Exam>> {
         Ok(self
-            .client
-            .reqwest_client()
-            .post(format!("{}/api/ks_xs_kslb", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
-                "xq":term,
-                "yhdm":self.client.account().user,
-                "dm":"学分制考试",
-                "yhid":self.get_authorizationid().await?,
-            }))
-            .send()
+            .post(
+                "/api/ks_xs_kslb",
+                &json!({
+                    "xq":term,
+                    "yhdm":self.client.account().user,
+                    "dm":"学分制考试",
+                    "yhid":self.get_authorizationid().await?,
+                }),
+                true,
+            )
             .await?
             .json()
             .await?)
@@ -163,16 +302,15 @@ impl<C: Client> JwqywxApplication<C> {
 
     pub async fn get_evaluatable_class(&self, term: String) -> Result<Message<EvaluatableClass>> {
         Ok(self
-            .client
-            .reqwest_client()
-            .post(format!("{}/api/pj_xspj_kcxx", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
-                "pjxq":term,
-                "xh":self.client.account().user,
-                "yhid":self.get_authorizationid().await?,
-            }))
-            .send()
+            .post(
+                "/api/pj_xspj_kcxx",
+                &json!({
+                    "pjxq":term,
+                    "xh":self.client.account().user,
+                    "yhid":self.get_authorizationid().await?,
+                }),
+                true,
+            )
             .await?
             .json()
             .await?)
@@ -194,11 +332,9 @@ impl<C: Client> JwqywxApplication<C> {
             .collect::<Vec<String>>()
             .join(",")
             + ",";
-        self.client
-            .reqwest_client()
-            .post(format!("{}/api/pj_insert_xspj", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
+        self.post(
+            "/api/pj_insert_xspj",
+            &json!({
                 "pjxq":term,
                 "yhdm":self.client.account().user,
                 "jsdm":evaluatable_class.teacher_code,
@@ -207,67 +343,102 @@ impl<C: Client> JwqywxApplication<C> {
                 "pjjg":pjjg,
                 "yjjy":comments,
                 "yhid":self.get_authorizationid().await?,
-            }))
-            .send()
-            .await?;
+            }),
+            // rejected with `401` before being applied, so replaying it is fine
+            false,
+        )
+        .await?;
         Ok(())
     }
 }
 
 #[derive(Serialize, Deserialize)]
 struct CachedJwqywxApplication {
+    #[serde(default)]
+    pub root: Option<String>,
     pub authorizationid: Option<String>,
     pub headers: HashMap<String, String>,
 }
-const CACHE_KEY: &str = "cached_jwqywx_application";
+
+impl PersistentProperty for CachedJwqywxApplication {
+    const KEY: &'static str = "cached_jwqywx_application";
+}
+
 impl<C: Client + Clone> CachedApplication<C> for JwqywxApplication<C> {
     async fn cache(&self) -> Result<()> {
-        self.client.properties().write().await.insert(
-            CACHE_KEY,
-            Property::String(serde_json::to_string(&CachedJwqywxApplication {
-                authorizationid: self.authorizationid.read().await.clone(),
-                headers: self
-                    .headers
-                    .read()
-                    .await
-                    .iter()
-                    .map(|(k, v)| {
-                        (
-                            k.as_str().to_string(),
-                            v.to_str().unwrap_or_default().to_string(),
-                        )
-                    })
-                    .collect(),
-            })?),
-        );
-
-        Ok(())
+        let cached = CachedJwqywxApplication {
+            root: Some(self.session.root.read().await.clone()),
+            authorizationid: self.session.authorizationid.read().await.clone(),
+            headers: self
+                .session
+                .headers
+                .read()
+                .await
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.as_str().to_string(),
+                        v.to_str().unwrap_or_default().to_string(),
+                    )
+                })
+                .collect(),
+        };
+        self.client
+            .properties()
+            .write()
+            .await
+            .insert_persistent(&cached)
     }
 
     async fn try_restore(client: &C) -> Option<Self>
     where
         Self: Sized,
     {
-        let properties = client.properties();
-        let cached = properties.read().await.get(CACHE_KEY)?.clone();
-        let cached: CachedJwqywxApplication = serde_json::from_str(
-            &cached
-                .get_string()
-                .context("Cached JwqywxApplication is not a string")
-                .ok()?,
-        )
-        .ok()?;
-        return Some(JwqywxApplication {
-            client: client.clone(),
-            headers: Arc::new(RwLock::new(
-                cached
-                    .headers
-                    .into_iter()
-                    .filter_map(|(k, v)| Some((k.parse().ok()?, HeaderValue::from_str(&v).ok()?)))
-                    .collect(),
-            )),
-            authorizationid: Arc::new(RwLock::new(cached.authorizationid)),
-        });
+        let cached: CachedJwqywxApplication =
+            client.properties().read().await.get_persistent().ok()??;
+        let app = Self::from_client(client).await.ok()?;
+        let mut authorizationid = app.session.authorizationid.write().await;
+        // a token got after the cache is kept
+        if authorizationid.is_none() {
+            *app.session.root.write().await = cached
+                .root
+                .unwrap_or_else(|| client.endpoints().jwqywx_api.clone());
+            *app.session.headers.write().await = cached
+                .headers
+                .into_iter()
+                .filter_map(|(k, v)| Some((k.parse().ok()?, HeaderValue::from_str(&v).ok()?)))
+                .collect();
+            *authorizationid = cached.authorizationid;
+        }
+        drop(authorizationid);
+        Some(app)
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod test {
+    use super::JwqywxApplication;
+    use crate::{base::app::AppVisitor, mock::MockCampus};
+
+    #[tokio::test]
+    async fn share_login_between_apps() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        let (a, b) = (
+            client.visit::<JwqywxApplication<_>>().await.unwrap(),
+            client
+                .clone()
+                .visit::<JwqywxApplication<_>>()
+                .await
+                .unwrap(),
+        );
+        let (first, second) = tokio::join!(a.login(), b.login());
+        assert_eq!(first.unwrap().token, second.unwrap().token);
+        assert_eq!(campus.jwqywx_tokens(), 1);
+
+        // the token is shared too
+        let c = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        assert!(c.get_grades().await.is_ok());
     }
 }
 
@@ -275,11 +446,10 @@ impl<C: Client + Clone> CachedApplication<C> for JwqywxApplication<C> {
 pub mod calendar {
     use crate::{
         base::client::Client,
+        error::{OptionExt, Result},
         extension::calendar::{CalendarParser, RawCourse, TermCalendarParser},
         impls::apps::wechat::jwqywx_type::{Message, calendar::SerdeRowCourses},
-        internals::fields::WECHAT_APP_API,
     };
-    use anyhow::{Context, Result};
     use serde_json::json;
 
     use super::JwqywxApplication;
@@ -290,16 +460,15 @@ pub mod calendar {
             term: String,
         ) -> Result<Vec<Vec<RawCourse>>> {
             Ok(self
-                .client
-                .reqwest_client()
-                .post(format!("{}/api/kb_xq_xh", WECHAT_APP_API))
-                .headers(self.headers.read().await.clone())
-                .json(&json!({
-                    "xh":self.client.account().user,
-                    "xq":term,
-                    "yhid":self.get_authorizationid().await?,
-                }))
-                .send()
+                .post(
+                    "/api/kb_xq_xh",
+                    &json!({
+                        "xh":self.client.account().user,
+                        "xq":term,
+                        "yhid":self.get_authorizationid().await?,
+                    }),
+                    true,
+                )
                 .await?
                 .json::<Message<SerdeRowCourses>>()
                 .await?
@@ -317,7 +486,7 @@ pub mod calendar {
                     .await?
                     .message
                     .first()
-                    .context("No terms available")?
+                    .ok_or_parse("jwqywx terms", "No terms available")?
                     .term
                     .clone(),
             )
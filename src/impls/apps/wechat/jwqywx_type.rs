@@ -1,13 +1,13 @@
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Message<T> {
     pub status: i32,
     pub message: Vec<T>,
     pub token: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct LoginUserData {
     #[serde(rename = "yhdm")]
     pub userid: String,
@@ -226,10 +226,10 @@ pub mod calendar {
         pub fields: HashMap<String, Value>,
     }
 
-    impl Into<Vec<RawCourse>> for SerdeRowCourses {
-        fn into(self) -> Vec<RawCourse> {
+    impl From<SerdeRowCourses> for Vec<RawCourse> {
+        fn from(value: SerdeRowCourses) -> Self {
             let courses = (1..=7).map(|index| {
-                let value = self.fields.get(&format!("kc{index}"));
+                let value = value.fields.get(&format!("kc{index}"));
                 if let Some(Value::String(course)) = value {
                     return course.clone();
                 }
@@ -240,11 +240,11 @@ pub mod calendar {
             let mut teachers = HashMap::new();
 
             for index in 1..=20 {
-                let name = self.fields.get(&format!("kcmc{index}"));
-                if let Some(Value::String(name)) = name {
-                    if let Some(Value::String(teacher)) = self.fields.get(&format!("skjs{index}")) {
-                        teachers.insert(name.clone(), teacher.clone());
-                    }
+                let name = value.fields.get(&format!("kcmc{index}"));
+                if let Some(Value::String(name)) = name
+                    && let Some(Value::String(teacher)) = value.fields.get(&format!("skjs{index}"))
+                {
+                    teachers.insert(name.clone(), teacher.clone());
                 }
             }
             courses
@@ -268,7 +268,7 @@ pub mod calendar {
                             if b.is_empty() {
                                 return a;
                             }
-                            return format!("{},/{}", a, b);
+                            format!("{},/{}", a, b)
                         })
                         .unwrap_or(String::new());
 
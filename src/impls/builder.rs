@@ -0,0 +1,287 @@
+use std::{sync::Arc, time::Duration};
+
+#[cfg(any(feature = "default-tls", feature = "rustls-tls", feature = "vendored"))]
+use reqwest::Certificate;
+use reqwest::{
+    Proxy,
+    header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT},
+    redirect::Policy,
+};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use tokio::sync::RwLock;
+
+use crate::{
+    base::{
+        captcha::CaptchaSolver, client::Account, credentials::CredentialProvider,
+        endpoints::Endpoints, properties::Properties, retry::RetryPolicy, transport::Transport,
+    },
+    error::{Error, Result},
+    impls::{
+        client::DefaultClient,
+        fixture::{Fixtures, RecordingTransport, ReplayTransport},
+        network::NetworkDetector,
+        transport::CookieTransport,
+    },
+    internals::{fields::DEFAULT_HEADERS, redirect::DEFAULT_MAX_REDIRECTS},
+};
+
+/// Build a [`DefaultClient`] with custom network settings.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use cczuni::impls::client::DefaultClient;
+/// let client = DefaultClient::builder()
+///     .account(cczuni::base::client::Account::new("user", "password"))
+///     .connect_timeout(Duration::from_secs(5))
+///     .user_agent("cczuni")
+///     .build()
+///     .unwrap();
+/// ```
+pub struct ClientBuilder {
+    account: Account,
+    headers: HeaderMap,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    #[cfg(any(feature = "default-tls", feature = "rustls-tls", feature = "vendored"))]
+    root_certificates: Vec<Certificate>,
+    redirect: Policy,
+    endpoints: Endpoints,
+    retry_policy: RetryPolicy,
+    max_redirects: usize,
+    credentials: Option<Arc<dyn CredentialProvider>>,
+    transport: Option<Arc<dyn Transport>>,
+    record: Option<Fixtures>,
+    auto_relogin: bool,
+    captcha_solver: Option<Arc<dyn CaptchaSolver>>,
+    network_detector: Option<NetworkDetector>,
+    error: Option<Error>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            account: Account::default(),
+            headers: DEFAULT_HEADERS.clone(),
+            connect_timeout: None,
+            timeout: None,
+            proxies: vec![],
+            #[cfg(any(feature = "default-tls", feature = "rustls-tls", feature = "vendored"))]
+            root_certificates: vec![],
+            redirect: Policy::none(),
+            endpoints: Endpoints::default(),
+            retry_policy: RetryPolicy::default(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            credentials: None,
+            transport: None,
+            record: None,
+            auto_relogin: false,
+            captcha_solver: None,
+            network_detector: None,
+            error: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(mut self, account: Account) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Replace the default Edge `User-Agent`.
+    pub fn user_agent(self, user_agent: impl AsRef<str>) -> Self {
+        self.header(USER_AGENT, user_agent)
+    }
+
+    /// Add a header sent with every request.
+    pub fn header(mut self, name: HeaderName, value: impl AsRef<str>) -> Self {
+        match HeaderValue::from_str(value.as_ref()) {
+            Ok(value) => {
+                self.headers.insert(name, value);
+            }
+            Err(_) => {
+                self.error.get_or_insert(Error::InvalidInput(format!(
+                    "Invalid value for header `{}`",
+                    name
+                )));
+            }
+        }
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of a whole request, from connecting until the body is read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    #[cfg(any(feature = "default-tls", feature = "rustls-tls", feature = "vendored"))]
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// The login flows inspect every `302` themselves,
+    /// so only change this if you won't use them.
+    ///
+    /// Default: [`Policy::none()`]
+    pub fn redirect(mut self, policy: Policy) -> Self {
+        self.redirect = policy;
+        self
+    }
+
+    /// Point the applications at other hosts, see [`Endpoints`].
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Default: [`RetryPolicy::default()`], use [`RetryPolicy::none()`] to disable retrying.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// How many redirects the login flows follow, see [`crate::base::client::Client::max_redirects`].
+    ///
+    /// Default: [`DEFAULT_MAX_REDIRECTS`]
+    pub fn max_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = max;
+        self
+    }
+
+    /// Read the password from `provider` instead of [`Account::password`].
+    pub fn credentials(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    /// Send the requests through `transport` instead of the built `reqwest::Client`,
+    /// e.g. a [`crate::impls::transport::MemoryTransport`] in tests.
+    ///
+    /// The cookie store keeps working, the network settings of this builder are ignored.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Save every request/response pair to `fixtures`, see [`RecordingTransport`].
+    pub fn record(mut self, fixtures: Fixtures) -> Self {
+        self.record = Some(fixtures);
+        self
+    }
+
+    /// Answer every request from `fixtures` saved by [`Self::record`], see [`ReplayTransport`].
+    pub fn replay(mut self, fixtures: Fixtures) -> Self {
+        match ReplayTransport::load(fixtures) {
+            Ok(transport) => self.transport = Some(Arc::new(transport)),
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+        self
+    }
+
+    /// Log in again once when an application finds its session expired.
+    ///
+    /// Default: `false`
+    pub fn auto_relogin(mut self, enabled: bool) -> Self {
+        self.auto_relogin = enabled;
+        self
+    }
+
+    /// Solve the captcha of the SSO login form with `solver`.
+    pub fn captcha_solver(mut self, solver: impl CaptchaSolver + 'static) -> Self {
+        self.captcha_solver = Some(Arc::new(solver));
+        self
+    }
+
+    /// Probe the network with other timeouts, see [`NetworkDetector`].
+    pub fn network_detector(mut self, detector: NetworkDetector) -> Self {
+        self.network_detector = Some(detector);
+        self
+    }
+
+    pub fn build(self) -> Result<DefaultClient> {
+        self.build_with_session(CookieStore::default(), Properties::new())
+    }
+
+    pub(crate) fn build_with_session(
+        self,
+        cookies: CookieStore,
+        mut properties: Properties,
+    ) -> Result<DefaultClient> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if let Some(detector) = self.network_detector {
+            properties.insert(Arc::new(detector));
+        }
+
+        let cookies = Arc::new(CookieStoreMutex::new(cookies));
+        let mut builder = reqwest::Client::builder()
+            .cookie_provider(cookies.clone())
+            .redirect(self.redirect);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        for proxy in self.proxies {
+            builder = builder.proxy(proxy);
+        }
+        #[cfg(any(feature = "default-tls", feature = "rustls-tls", feature = "vendored"))]
+        for certificate in self.root_certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| Error::InvalidInput(format!("Build http client failed: {}", e)))?;
+
+        let custom = self.transport.is_some();
+        let mut transport: Arc<dyn Transport> = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(client.clone()),
+        };
+        if let Some(fixtures) = self.record {
+            transport = Arc::new(RecordingTransport::wrap(transport, fixtures)?);
+        }
+        if custom {
+            transport = Arc::new(CookieTransport {
+                inner: transport,
+                cookies: cookies.clone(),
+            });
+        }
+
+        Ok(DefaultClient {
+            account: self.account,
+            client,
+            transport,
+            headers: self.headers,
+            endpoints: Arc::new(self.endpoints),
+            retry_policy: Arc::new(self.retry_policy),
+            max_redirects: self.max_redirects,
+            credentials: self.credentials,
+            auto_relogin: self.auto_relogin,
+            captcha_solver: self.captcha_solver,
+            cookies,
+            properties: Arc::new(RwLock::new(properties)),
+        })
+    }
+}
@@ -1,17 +1,29 @@
-#[cfg(feature = "lru-client")]
-use lru::LruCache;
-use reqwest::redirect::Policy;
-use reqwest_cookie_store::CookieStoreMutex;
+use cookie_store::Cookie;
+use reqwest::header::HeaderMap;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "lru-client")]
 use std::num::NonZeroUsize;
 #[cfg(feature = "lru-client")]
 use std::sync::LazyLock;
-use std::{collections::HashMap, sync::Arc};
-#[cfg(feature = "lru-client")]
-use tokio::sync::Mutex;
+use std::{convert::Infallible, fmt::Debug, path::Path, sync::Arc};
 use tokio::sync::RwLock;
 
-use crate::base::client::{Account, Client, Property};
+#[cfg(feature = "lru-client")]
+use crate::impls::pool::{ClientPool, PooledClient};
+use crate::{
+    base::{
+        captcha::CaptchaSolver,
+        client::{Account, Client},
+        credentials::{CredentialProvider, Secret},
+        endpoints::Endpoints,
+        properties::Properties,
+        retry::RetryPolicy,
+        transport::Transport,
+    },
+    error::{Error, Result},
+    impls::builder::ClientBuilder,
+};
 
 #[cfg(feature = "lru-client")]
 static CACHE_SIZE: LazyLock<NonZeroUsize> = LazyLock::new(|| {
@@ -24,15 +36,45 @@ static CACHE_SIZE: LazyLock<NonZeroUsize> = LazyLock::new(|| {
 });
 
 #[cfg(feature = "lru-client")]
-static CLIENT_CACHE: LazyLock<Mutex<LruCache<Account, DefaultClient>>> =
-    LazyLock::new(|| Mutex::new(LruCache::new(*CACHE_SIZE)));
+static CLIENT_POOL: LazyLock<ClientPool> =
+    LazyLock::new(|| ClientPool::builder().capacity(*CACHE_SIZE).build());
 
-#[derive(Debug, Clone)]
+/// What [`DefaultClient::save_session`] writes to disk.
+#[derive(Serialize, Deserialize)]
+struct Session<P> {
+    cookies: Vec<Cookie<'static>>,
+    properties: P,
+}
+
+#[derive(Clone)]
 pub struct DefaultClient {
-    account: Account,
-    client: reqwest::Client,
-    cookies: Arc<CookieStoreMutex>,
-    properties: Arc<RwLock<HashMap<&'static str, Property>>>,
+    pub(crate) account: Account,
+    pub(crate) client: reqwest::Client,
+    pub(crate) transport: Arc<dyn Transport>,
+    pub(crate) headers: HeaderMap,
+    pub(crate) endpoints: Arc<Endpoints>,
+    pub(crate) retry_policy: Arc<RetryPolicy>,
+    pub(crate) max_redirects: usize,
+    pub(crate) credentials: Option<Arc<dyn CredentialProvider>>,
+    pub(crate) auto_relogin: bool,
+    pub(crate) captcha_solver: Option<Arc<dyn CaptchaSolver>>,
+    pub(crate) cookies: Arc<CookieStoreMutex>,
+    pub(crate) properties: Arc<RwLock<Properties>>,
+}
+
+impl Debug for DefaultClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultClient")
+            .field("account", &self.account)
+            .field("headers", &self.headers)
+            .field("endpoints", &self.endpoints)
+            .field("retry_policy", &self.retry_policy)
+            .field("max_redirects", &self.max_redirects)
+            .field("credentials", &self.credentials.is_some())
+            .field("auto_relogin", &self.auto_relogin)
+            .field("captcha_solver", &self.captcha_solver.is_some())
+            .finish_non_exhaustive()
+    }
 }
 
 impl Default for DefaultClient {
@@ -42,21 +84,68 @@ impl Default for DefaultClient {
 }
 
 impl DefaultClient {
+    /// Panics if the http client can't be built, e.g. the TLS backend fails to load,
+    /// see [`DefaultClient::try_new`].
     pub fn new(account: Account) -> Self {
-        let cookies = Arc::new(CookieStoreMutex::default());
-        Self {
-            account,
-            client: reqwest::Client::builder()
-                .cookie_provider(cookies.clone())
-                .redirect(Policy::none())
-                .build()
-                .unwrap(),
+        Self::try_new(account).expect("Build default http client failed")
+    }
+
+    pub fn try_new(account: Account) -> Result<Self> {
+        Self::builder().account(account).build()
+    }
+
+    /// Configure timeouts, proxy, user agent... see [`ClientBuilder`].
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Serialize the cookies and the [`crate::base::properties::PersistentProperty`] values,
+    /// e.g. the login connect type and the cached applications.
+    pub async fn to_bytes(&self) -> Result<Vec<u8>> {
+        let cookies = self
+            .cookies
+            .lock()
+            .unwrap()
+            .iter_unexpired()
+            .cloned()
+            .collect();
+        let properties = self.properties.read().await;
+
+        serde_json::to_vec(&Session {
             cookies,
-            properties: Arc::new(RwLock::new(HashMap::new())),
-        }
+            properties: &*properties,
+        })
+        .map_err(|e| Error::parse_with("session", "Serialize session failed", e))
+    }
+
+    /// Restore a client from [`DefaultClient::to_bytes`], expired cookies are dropped.
+    pub fn from_bytes(account: Account, bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes_with(Self::builder().account(account), bytes)
+    }
+
+    /// Same as [`DefaultClient::from_bytes`] but keep the settings of `builder`.
+    pub fn from_bytes_with(builder: ClientBuilder, bytes: &[u8]) -> Result<Self> {
+        let session: Session<Properties> = serde_json::from_slice(bytes)
+            .map_err(|e| Error::parse_with("session", "Deserialize session failed", e))?;
+        let cookies =
+            CookieStore::from_cookies(session.cookies.into_iter().map(Ok::<_, Infallible>), false)
+                .unwrap_or_default();
+
+        builder.build_with_session(cookies, session.properties)
     }
 
-    pub fn account(user: impl Into<String>, password: impl Into<String>) -> Self {
+    /// Save the session to `path`, see [`DefaultClient::to_bytes`].
+    pub async fn save_session(&self, path: impl AsRef<Path>) -> Result<()> {
+        tokio::fs::write(path, self.to_bytes().await?).await?;
+        Ok(())
+    }
+
+    /// Load a session saved by [`DefaultClient::save_session`].
+    pub async fn load_session(account: Account, path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(account, &tokio::fs::read(path).await?)
+    }
+
+    pub fn account(user: impl Into<String>, password: impl Into<Secret>) -> Self {
         Self::new(Account::new(user, password))
     }
 
@@ -71,14 +160,13 @@ impl DefaultClient {
     /// 使用 LRU 缓存创建或复用 DefaultClient 实例
     /// 对于相同 Account，返回缓存的单例；否则创建新实例并缓存
     #[cfg(feature = "lru-client")]
+    #[deprecated(note = "use an explicit `ClientPool` instead")]
     pub async fn lru_new(account: Account) -> Self {
-        let mut cache = CLIENT_CACHE.lock().await;
-        if let Some(client) = cache.get(&account) {
-            return client.clone();
-        }
-        let client = Self::new(account.clone());
-        cache.put(account, client.clone());
-        client
+        CLIENT_POOL
+            .acquire(account)
+            .await
+            .map(PooledClient::into_inner)
+            .expect("Build default client failed")
     }
 }
 
@@ -91,11 +179,101 @@ impl Client for DefaultClient {
         self.client.clone()
     }
 
+    fn transport(&self) -> Arc<dyn Transport> {
+        self.transport.clone()
+    }
+
     fn cookies(&self) -> Arc<CookieStoreMutex> {
         self.cookies.clone()
     }
 
-    fn properties(&self) -> Arc<RwLock<HashMap<&'static str, Property>>> {
+    fn properties(&self) -> Arc<RwLock<Properties>> {
         self.properties.clone()
     }
+
+    async fn password(&self) -> Result<Secret> {
+        match &self.credentials {
+            Some(provider) => provider.password(&self.account.user).await,
+            None => Ok(self.account.password.clone()),
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn max_redirects(&self) -> usize {
+        self.max_redirects
+    }
+
+    fn auto_relogin(&self) -> bool {
+        self.auto_relogin
+    }
+
+    fn captcha_solver(&self) -> Option<Arc<dyn CaptchaSolver>> {
+        self.captcha_solver.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reqwest::Url;
+
+    use super::DefaultClient;
+    use crate::{
+        base::client::{Account, Client},
+        impls::login::{sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType},
+    };
+
+    #[tokio::test]
+    async fn session_roundtrip() {
+        let client = DefaultClient::account("user", "password");
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        client
+            .cookies()
+            .lock()
+            .unwrap()
+            .parse("CASTGC=ticket; Path=/", &url)
+            .unwrap();
+        client
+            .properties()
+            .write()
+            .await
+            .insert_persistent(&SSOLoginConnectType::WEBVPN)
+            .unwrap();
+
+        let bytes = client.to_bytes().await.unwrap();
+        let restored = DefaultClient::from_bytes(Account::new("user", "password"), &bytes).unwrap();
+
+        assert!(
+            restored
+                .cookies()
+                .lock()
+                .unwrap()
+                .contains("sso.cczu.edu.cn", "/", "CASTGC")
+        );
+        assert_eq!(
+            restored.sso_login_connect_type().await,
+            Some(SSOLoginConnectType::WEBVPN)
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        client.save_session(&path).await.unwrap();
+        let loaded = DefaultClient::load_session(Account::new("user", "password"), &path)
+            .await
+            .unwrap();
+        assert_eq!(
+            loaded.sso_login_connect_type().await,
+            Some(SSOLoginConnectType::WEBVPN)
+        );
+    }
 }
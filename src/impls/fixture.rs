@@ -0,0 +1,424 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use reqwest::{
+    Method, Request, StatusCode, Url,
+    header::{
+        CONTENT_LENGTH, HeaderMap, HeaderName, HeaderValue, LOCATION, SET_COOKIE, TRANSFER_ENCODING,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    base::transport::{Transport, TransportFuture},
+    error::{Error, Result},
+    impls::transport::{MemoryTransport, MockResponse},
+    internals::request::buffer_response,
+};
+
+const REDACTED: &str = "***";
+
+/// Query parameters and json fields carrying a ticket or a secret, never written to a fixture.
+const SECRET_PARAMS: [&str; 5] = ["ticket", "token", "password", "userpwd", "loginKey"];
+
+/// A directory of recorded request/response pairs, one json file per exchange.
+///
+/// Only the method and url of a request are saved, the cookies are saved
+/// as `name=***`, the secret query parameters, also in redirect locations,
+/// and the secret fields of json bodies as `***`.
+///
+/// ```no_run
+/// # use cczuni::impls::{client::DefaultClient, fixture::Fixtures};
+/// // record once with a real account
+/// let client = DefaultClient::builder()
+///     .record(Fixtures::new("fixtures/jwcas").redact("2300000000"))
+///     .build()
+///     .unwrap();
+/// // then replay in tests, without the campus network
+/// let client = DefaultClient::builder()
+///     .replay(Fixtures::new("fixtures/jwcas").redact("2300000000"))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    dir: PathBuf,
+    redactions: Vec<String>,
+}
+
+impl Fixtures {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            redactions: vec![],
+        }
+    }
+
+    /// Replace `value` with `***` in the urls, headers and bodies, e.g. the student id.
+    ///
+    /// Redact the same values when replaying, so the urls still match.
+    pub fn redact(mut self, value: impl Into<String>) -> Self {
+        let value = value.into();
+        if !value.is_empty() {
+            self.redactions.push(value);
+        }
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn redact_text(&self, text: &str) -> String {
+        self.redactions
+            .iter()
+            .fold(text.to_string(), |text, value| {
+                text.replace(value, REDACTED)
+            })
+    }
+
+    fn scrub_url(&self, url: &Url) -> String {
+        let mut url = url.clone();
+        if url.query().is_some() {
+            let pairs: Vec<(String, String)> = url
+                .query_pairs()
+                .map(|(key, value)| match SECRET_PARAMS.contains(&key.as_ref()) {
+                    true => (key.into_owned(), REDACTED.to_string()),
+                    false => (key.into_owned(), value.into_owned()),
+                })
+                .collect();
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        self.redact_text(url.as_str())
+    }
+
+    /// Scrub a redirect location like [`Fixtures::scrub_url`], it may be relative.
+    fn scrub_location(&self, location: &str) -> String {
+        if let Ok(url) = Url::parse(location) {
+            return self.scrub_url(&url);
+        }
+        let Some((path, query)) = location.split_once('?') else {
+            return self.redact_text(location);
+        };
+        let (query, fragment) = match query.split_once('#') {
+            Some((query, fragment)) => (query, Some(fragment)),
+            None => (query, None),
+        };
+        let query: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((key, _)) if SECRET_PARAMS.contains(&key) => format!("{}={}", key, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect();
+        let mut location = format!("{}?{}", path, query.join("&"));
+        if let Some(fragment) = fragment {
+            location = format!("{}#{}", location, fragment);
+        }
+        self.redact_text(&location)
+    }
+
+    /// Redact a text body, the secret fields of a json one are replaced first.
+    fn scrub_body(&self, text: &str) -> String {
+        match serde_json::from_str::<Value>(text) {
+            Ok(mut json) if json.is_object() || json.is_array() => {
+                scrub_json(&mut json);
+                self.redact_text(&json.to_string())
+            }
+            _ => self.redact_text(text),
+        }
+    }
+
+    /// The json files of the directory, in the order they were recorded.
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = fs::read_dir(&self.dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        files.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        files.sort();
+        Ok(files)
+    }
+}
+
+fn scrub_json(json: &mut Value) {
+    match json {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                match SECRET_PARAMS.contains(&key.as_str()) && !value.is_null() {
+                    true => *value = Value::String(REDACTED.to_string()),
+                    false => scrub_json(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(scrub_json),
+        _ => {}
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+    /// The body is not UTF-8 and saved as base64.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    base64: bool,
+}
+
+impl Fixture {
+    fn capture(
+        fixtures: &Fixtures,
+        method: &Method,
+        url: &Url,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Self {
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| **name != CONTENT_LENGTH && **name != TRANSFER_ENCODING)
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                let value = match *name {
+                    SET_COOKIE => format!("{}={}", value.split('=').next()?, REDACTED),
+                    LOCATION => fixtures.scrub_location(value),
+                    _ => fixtures.redact_text(value),
+                };
+                Some((name.to_string(), value))
+            })
+            .collect();
+        let (body, base64) = match std::str::from_utf8(body) {
+            Ok(text) => (fixtures.scrub_body(text), false),
+            Err(_) => (BASE64_STANDARD.encode(body), true),
+        };
+        Self {
+            method: method.to_string(),
+            url: fixtures.scrub_url(url),
+            status: status.as_u16(),
+            headers,
+            body,
+            base64,
+        }
+    }
+
+    fn into_response(self) -> Result<(Method, String, MockResponse)> {
+        let method = Method::from_bytes(self.method.as_bytes())
+            .map_err(|e| Error::parse_with("fixture", "Invalid method", e))?;
+        let status = StatusCode::from_u16(self.status)
+            .map_err(|e| Error::parse_with("fixture", "Invalid status", e))?;
+        let body = match self.base64 {
+            true => BASE64_STANDARD
+                .decode(&self.body)
+                .map_err(|e| Error::parse_with("fixture", "Invalid base64 body", e))?,
+            false => self.body.into_bytes(),
+        };
+        let mut response = MockResponse::new(status).body(body);
+        for (name, value) in self.headers {
+            if let Ok(name) = HeaderName::from_bytes(name.as_bytes())
+                && let Ok(value) = HeaderValue::from_str(&value)
+            {
+                response = response.header(name, value);
+            }
+        }
+        Ok((method, self.url, response))
+    }
+
+    fn file_name(&self, index: usize) -> String {
+        let path = Url::parse(&self.url)
+            .map(|url| format!("{}{}", url.host_str().unwrap_or_default(), url.path()))
+            .unwrap_or_default();
+        let slug: String = path
+            .chars()
+            .map(|char| match char.is_ascii_alphanumeric() {
+                true => char,
+                false => '_',
+            })
+            .take(64)
+            .collect();
+        format!(
+            "{:04}-{}-{}.json",
+            index,
+            self.method,
+            slug.trim_matches('_')
+        )
+    }
+}
+
+/// Save every exchange sent through `inner` to [`Fixtures`].
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    fixtures: Fixtures,
+    counter: AtomicUsize,
+}
+
+impl RecordingTransport {
+    /// Create the directory, the new fixtures are numbered after the existing ones.
+    pub fn new(inner: impl Transport + 'static, fixtures: Fixtures) -> Result<Self> {
+        Self::wrap(Arc::new(inner), fixtures)
+    }
+
+    pub(crate) fn wrap(inner: Arc<dyn Transport>, fixtures: Fixtures) -> Result<Self> {
+        fs::create_dir_all(&fixtures.dir)?;
+        let counter = AtomicUsize::new(fixtures.files()?.len());
+        Ok(Self {
+            inner,
+            fixtures,
+            counter,
+        })
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn execute(&self, request: Request) -> TransportFuture {
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let inner = self.inner.clone();
+        let fixtures = self.fixtures.clone();
+        let index = self.counter.fetch_add(1, Ordering::Relaxed);
+        Box::pin(async move {
+            let (body, response) = buffer_response(inner.execute(request).await?).await?;
+            let fixture = Fixture::capture(
+                &fixtures,
+                &method,
+                &url,
+                response.status(),
+                response.headers(),
+                &body,
+            );
+            fs::write(
+                fixtures.dir.join(fixture.file_name(index)),
+                serde_json::to_string_pretty(&fixture)?,
+            )?;
+            Ok(response)
+        })
+    }
+}
+
+/// Answer from [`Fixtures`] saved by [`RecordingTransport`], without any network.
+///
+/// The responses of one url are served in the recorded order,
+/// an url which wasn't recorded is answered with `404`.
+pub struct ReplayTransport {
+    inner: MemoryTransport,
+    fixtures: Fixtures,
+}
+
+impl ReplayTransport {
+    pub fn load(fixtures: Fixtures) -> Result<Self> {
+        let inner = MemoryTransport::new();
+        for path in fixtures.files()? {
+            let fixture: Fixture = serde_json::from_str(&fs::read_to_string(path)?)?;
+            let (method, url, response) = fixture.into_response()?;
+            inner.add_route(method, url, response);
+        }
+        Ok(Self { inner, fixtures })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn execute(&self, mut request: Request) -> TransportFuture {
+        if let Ok(url) = Url::parse(&self.fixtures.scrub_url(request.url())) {
+            *request.url_mut() = url;
+        }
+        self.inner.execute(request)
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod test {
+    use std::path::Path;
+
+    use super::Fixtures;
+    use crate::{
+        base::app::AppVisitor,
+        impls::{
+            apps::{sso::jwcas::JwcasApplication, wechat::jwqywx::JwqywxApplication},
+            client::DefaultClient,
+            login::sso::SSOUniversalLogin,
+        },
+        mock::{MOCK_PASSWORD, MOCK_USER, MockCampus},
+    };
+
+    fn assert_scrubbed(dir: &Path, secrets: &[&str]) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            for secret in secrets {
+                assert!(!content.contains(secret), "{} leaked", secret);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixtures = Fixtures::new(dir.path()).redact(MOCK_USER);
+
+        let campus = MockCampus::start().await.unwrap();
+        let endpoints = campus.endpoints();
+        let client = campus
+            .client_builder()
+            .record(fixtures.clone())
+            .build()
+            .unwrap();
+        client.sso_universal_login().await.unwrap();
+        let app = JwcasApplication::from_client_login(client).await.unwrap();
+        let recorded = app.get_gradeinfo_vec().await.unwrap();
+        drop(campus);
+
+        assert_scrubbed(dir.path(), &[MOCK_USER, "TGT-", "ST-", "session-"]);
+
+        let app = JwcasApplication {
+            client: DefaultClient::builder()
+                .endpoints(endpoints.clone())
+                .replay(fixtures)
+                .build()
+                .unwrap(),
+            root: endpoints.jwcas,
+        };
+        let replayed = app.get_gradeinfo_vec().await.unwrap();
+        assert_eq!(replayed.len(), recorded.len());
+        assert_eq!(replayed[0].name, recorded[0].name);
+        assert_eq!(replayed[0].grade, recorded[0].grade);
+    }
+
+    #[tokio::test]
+    async fn record_jwqywx_login() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixtures = Fixtures::new(dir.path()).redact(MOCK_USER);
+
+        let campus = MockCampus::start().await.unwrap();
+        let endpoints = campus.endpoints();
+        let client = campus
+            .client_builder()
+            .record(fixtures.clone())
+            .build()
+            .unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        let token = app.login().await.unwrap().token.unwrap();
+        let recorded = app.get_grades().await.unwrap().message;
+        drop(campus);
+
+        assert_scrubbed(dir.path(), &[MOCK_USER, MOCK_PASSWORD, &token]);
+
+        let client = DefaultClient::builder()
+            .account(crate::base::client::Account::new(MOCK_USER, MOCK_PASSWORD))
+            .endpoints(endpoints)
+            .replay(fixtures)
+            .build()
+            .unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        app.login().await.unwrap();
+        let replayed = app.get_grades().await.unwrap().message;
+        assert_eq!(replayed.len(), recorded.len());
+    }
+}
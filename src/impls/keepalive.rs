@@ -0,0 +1,354 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use tokio::{
+    sync::Notify,
+    time::{self, MissedTickBehavior},
+};
+
+use crate::{
+    base::app::CachedApplication,
+    error::{Error, Result},
+    impls::{
+        apps::wechat::jwqywx::JwqywxApplication,
+        client::DefaultClient,
+        login::{
+            sso::SSOUniversalLogin, sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType,
+        },
+    },
+};
+
+type EventHook = Arc<dyn Fn(&KeepAliveEvent) + Send + Sync>;
+
+/// Default of [`KeepAlive::interval`].
+pub const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// A system whose session is pinged by [`KeepAlive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepAliveSystem {
+    Sso,
+    WebVpn,
+    Jwqywx,
+}
+
+/// What a ping of [`KeepAlive`] found.
+#[derive(Debug)]
+pub enum KeepAliveEvent {
+    Alive(KeepAliveSystem),
+    /// The session was gone and the login succeeded.
+    Relogged(KeepAliveSystem),
+    /// The ping or the login failed, the next tick tries again.
+    Failed(KeepAliveSystem, Error),
+}
+
+/// Ping the logged in systems of a client periodically,
+/// and log in again when a session is gone.
+///
+/// The SSO or WebVPN session is pinged if [`SSOUniversalLogin::sso_universal_login`]
+/// was called, jwqywx if an application is given by [`KeepAlive::jwqywx`].
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use cczuni::impls::client::DefaultClient;
+/// # async fn example(client: DefaultClient) {
+/// let handle = client
+///     .keep_alive()
+///     .interval(Duration::from_secs(300))
+///     .on_event(|event| println!("{:?}", event))
+///     .spawn();
+/// // ...
+/// handle.cancel();
+/// # }
+/// ```
+pub struct KeepAlive {
+    client: DefaultClient,
+    interval: Duration,
+    jwqywx: Option<JwqywxApplication<DefaultClient>>,
+    on_event: Option<EventHook>,
+}
+
+impl Debug for KeepAlive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeepAlive")
+            .field("interval", &self.interval)
+            .field("jwqywx", &self.jwqywx.is_some())
+            .field("on_event", &self.on_event.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DefaultClient {
+    /// Keep the sessions of this client alive in the background, see [`KeepAlive`].
+    pub fn keep_alive(&self) -> KeepAlive {
+        KeepAlive {
+            client: self.clone(),
+            interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            jwqywx: None,
+            on_event: None,
+        }
+    }
+}
+
+impl KeepAlive {
+    /// Default: [`DEFAULT_KEEP_ALIVE_INTERVAL`], at least 1ms
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Also keep the token of `app` alive, it is cached again after a login if it was cached.
+    pub fn jwqywx(mut self, app: JwqywxApplication<DefaultClient>) -> Self {
+        self.jwqywx = Some(app);
+        self
+    }
+
+    /// Called with every ping result, e.g. to log the failed logins.
+    pub fn on_event(mut self, hook: impl Fn(&KeepAliveEvent) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(Arc::new(hook));
+        self
+    }
+
+    /// Ping every logged in system once, the systems not logged in are skipped.
+    pub async fn check(&self) -> Vec<KeepAliveEvent> {
+        let mut events = vec![];
+        if let Some(connect_type) = self.client.sso_login_connect_type().await {
+            events.push(self.check_sso(connect_type).await);
+        }
+        if let Some(app) = &self.jwqywx
+            && let Some(event) = check_jwqywx(app).await
+        {
+            events.push(event);
+        }
+        if let Some(hook) = &self.on_event {
+            events.iter().for_each(|event| hook(event));
+        }
+        events
+    }
+
+    async fn check_sso(&self, connect_type: SSOLoginConnectType) -> KeepAliveEvent {
+        let (system, alive) = match connect_type {
+            SSOLoginConnectType::WEBVPN => (
+                KeepAliveSystem::WebVpn,
+                self.client.webvpn_session_alive().await,
+            ),
+            SSOLoginConnectType::COMMON => {
+                (KeepAliveSystem::Sso, self.client.sso_session_alive().await)
+            }
+        };
+        match alive {
+            Ok(true) => KeepAliveEvent::Alive(system),
+            Ok(false) => match self.client.sso_universal_login().await {
+                Ok(_) => KeepAliveEvent::Relogged(system),
+                Err(error) => KeepAliveEvent::Failed(system, error),
+            },
+            // offline, the session may still be alive
+            Err(error) => KeepAliveEvent::Failed(system, error),
+        }
+    }
+
+    /// Ping every [`Self::interval`] on tokio, the first ping is after one interval.
+    pub fn spawn(self) -> KeepAliveHandle {
+        let cancel = Arc::new(Notify::new());
+        let cancelled = cancel.clone();
+        let task = tokio::spawn(async move {
+            let mut interval =
+                time::interval_at(time::Instant::now() + self.interval, self.interval);
+            // a suspended machine pings once when it wakes up
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancelled.notified() => break,
+                    _ = interval.tick() => {
+                        self.check().await;
+                    }
+                }
+            }
+        });
+        KeepAliveHandle {
+            cancel,
+            task: Some(task),
+        }
+    }
+}
+
+/// `None` if the application isn't logged in.
+async fn check_jwqywx(app: &JwqywxApplication<DefaultClient>) -> Option<KeepAliveEvent> {
+    let system = KeepAliveSystem::Jwqywx;
+    let event = match app.session_alive().await {
+        Err(Error::NotLoggedIn) => return None,
+        Ok(true) => KeepAliveEvent::Alive(system),
+        Ok(false) => match relogin_jwqywx(app).await {
+            Ok(()) => KeepAliveEvent::Relogged(system),
+            Err(error) => KeepAliveEvent::Failed(system, error),
+        },
+        Err(error) => KeepAliveEvent::Failed(system, error),
+    };
+    Some(event)
+}
+
+async fn relogin_jwqywx(app: &JwqywxApplication<DefaultClient>) -> Result<()> {
+    app.login().await?;
+    if app.is_cached().await {
+        app.cache().await?;
+    }
+    Ok(())
+}
+
+/// Stops the task of [`KeepAlive::spawn`] when cancelled or dropped,
+/// a ping in progress is finished first.
+#[derive(Debug)]
+pub struct KeepAliveHandle {
+    cancel: Arc<Notify>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl KeepAliveHandle {
+    pub fn cancel(&self) {
+        self.cancel.notify_one();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.as_ref().is_none_or(|task| task.is_finished())
+    }
+
+    /// Cancel and wait until the task has stopped.
+    pub async fn stop(mut self) {
+        self.cancel();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for KeepAliveHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod test {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::{KeepAliveEvent, KeepAliveSystem};
+    use crate::{
+        base::{app::AppVisitor, client::Client, endpoints::Endpoints},
+        error::Error,
+        impls::{
+            apps::wechat::jwqywx::JwqywxApplication, login::sso::SSOUniversalLogin,
+            login::sso_type::SSOLoginConnectType, network::NetworkDetect,
+        },
+        mock::MockCampus,
+    };
+
+    #[tokio::test]
+    async fn relogin_expired_sessions() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        let keep_alive = client.keep_alive().jwqywx(app.clone());
+        assert!(keep_alive.check().await.is_empty());
+
+        client.sso_universal_login().await.unwrap();
+        app.login().await.unwrap();
+        let events = keep_alive.check().await;
+        assert!(matches!(
+            events[..],
+            [
+                KeepAliveEvent::Alive(KeepAliveSystem::Sso),
+                KeepAliveEvent::Alive(KeepAliveSystem::Jwqywx)
+            ]
+        ));
+
+        campus.expire_sessions();
+        let events = keep_alive.check().await;
+        assert!(matches!(
+            events[..],
+            [
+                KeepAliveEvent::Relogged(KeepAliveSystem::Sso),
+                KeepAliveEvent::Relogged(KeepAliveSystem::Jwqywx)
+            ]
+        ));
+        assert!(app.get_grades().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn webvpn_session() {
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let client = campus.client().unwrap();
+        client.sso_universal_login().await.unwrap();
+        let keep_alive = client.keep_alive();
+        assert!(matches!(
+            keep_alive.check().await[..],
+            [KeepAliveEvent::Alive(KeepAliveSystem::WebVpn)]
+        ));
+        campus.expire_sessions();
+        assert!(matches!(
+            keep_alive.check().await[..],
+            [KeepAliveEvent::Relogged(KeepAliveSystem::WebVpn)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn offline_without_relogin() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus
+            .client_builder()
+            .endpoints(Endpoints {
+                sso: "http://127.0.0.1:1".into(),
+                ..campus.endpoints()
+            })
+            .build()
+            .unwrap();
+        client
+            .properties()
+            .write()
+            .await
+            .insert_persistent(&SSOLoginConnectType::COMMON)
+            .unwrap();
+        assert!(matches!(
+            client.keep_alive().check().await[..],
+            [KeepAliveEvent::Failed(
+                KeepAliveSystem::Sso,
+                Error::Network(_)
+            )]
+        ));
+        // a login would have probed the network first
+        assert!(client.network_detector().await.cached().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn spawn_and_cancel() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        client.sso_universal_login().await.unwrap();
+
+        let pings = Arc::new(AtomicUsize::new(0));
+        let counter = pings.clone();
+        let handle = client
+            .keep_alive()
+            .interval(Duration::from_millis(20))
+            .on_event(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .spawn();
+        tokio::time::sleep(Duration::from_millis(110)).await;
+        assert!(!handle.is_finished());
+        handle.stop().await;
+
+        let stopped = pings.load(Ordering::Relaxed);
+        assert!(stopped >= 2);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(pings.load(Ordering::Relaxed), stopped);
+
+        let handle = client.keep_alive().interval(Duration::ZERO).spawn();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!handle.is_finished());
+        handle.stop().await;
+    }
+}
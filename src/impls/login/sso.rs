@@ -1,166 +1,534 @@
 use std::{collections::HashMap, future::Future};
 
-use super::sso_type::{ElinkLoginInfo, SSOLoginConnectType, SSOUniversalLoginInfo};
+use super::sso_type::{
+    ElinkLoginInfo, SSOLoginConnectType, SSOLoginOutcome, SSOUniversalLoginInfo, SSOVerification,
+    VerificationChannel,
+};
 use crate::{
     base::client::Client,
+    error::{Error, OptionExt, Result},
+    impls::network::NetworkDetect,
     internals::{
         cookies_io::CookiesIOExt,
-        fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, ROOT_VPN_URL},
-        recursion::recursion_redirect_handle,
+        redirect::{follow_get, follow_response},
+        request::{RequestBuilderExt, buffer_response},
+        session::{end_session, is_sso_login_page},
+        single_flight::client_flight,
     },
 };
-use anyhow::{Context, Result, bail};
 use base64::{Engine, prelude::BASE64_STANDARD};
-use reqwest::{Response, StatusCode, cookie::Cookie, header::LOCATION};
+use reqwest::{Method, Response, StatusCode, Url, header::LOCATION};
 use scraper::{Html, Selector};
+use zeroize::Zeroizing;
+
+/// How many times a captcha is solved in one login.
+const CAPTCHA_ATTEMPTS: usize = 3;
 
 pub trait SSOUniversalLogin {
     /// This method implements [`ROOT_SSO`] url login.
     ///
     /// You can only get the ElinkLoginInfo in WebVPN Mode...
+    ///
+    /// Concurrent calls on the clones of a client share one login.
     fn sso_universal_login(&self) -> impl Future<Output = Result<Option<ElinkLoginInfo>>>;
 
     fn sso_service_login(
         &self,
         service: impl Into<String>,
     ) -> impl Future<Output = Result<Response>>;
+
+    /// Submit the login form of the service without following the redirect,
+    /// the reason of a failure is returned instead of an [`Error`].
+    fn sso_login_outcome(
+        &self,
+        service: impl Into<String>,
+    ) -> impl Future<Output = Result<SSOLoginOutcome>>;
+
+    /// Ask the SSO to send the code of a [`Error::VerificationRequired`],
+    /// not needed if [`SSOVerification::can_send_code`] is `false`.
+    fn sso_send_verification_code(
+        &self,
+        verification: &SSOVerification,
+    ) -> impl Future<Output = Result<()>>;
+
+    /// Submit the code and finish the interrupted login like [`Self::sso_service_login`].
+    ///
+    /// A wrong code is returned as another [`Error::VerificationRequired`],
+    /// an accepted one stores the [`SSOLoginConnectType`] like a login without verification.
+    fn sso_verify(
+        &self,
+        verification: &SSOVerification,
+        code: &str,
+    ) -> impl Future<Output = Result<Response>>;
+
+    /// End the SSO session and forget its cookies and [`SSOLoginConnectType`],
+    /// through the WebVPN if the login went through it.
+    fn sso_logout(&self) -> impl Future<Output = Result<()>>;
 }
 
 impl<C: Client + Clone + Send> SSOUniversalLogin for C {
     async fn sso_universal_login(&self) -> Result<Option<ElinkLoginInfo>> {
-        let login = universal_sso_login(self.clone()).await?;
-        self.properties().write().await.insert(
-            SSOLoginConnectType::key(),
-            login.login_connect_type.clone().into(),
-        );
-
-        match login.login_connect_type {
-            SSOLoginConnectType::WEBVPN => Ok(Some(serde_json::from_str(&String::from_utf8(
-                BASE64_STANDARD.decode(
-                    login
-                        .response
-                        .cookies()
-                        .filter(|cookie| cookie.name() == "clientInfo")
-                        .collect::<Vec<Cookie>>()
-                        .first()
-                        .context("Get `EnlinkLoginInfo` Failed")?
-                        .value(),
-                )?,
-            )?)?)),
-            SSOLoginConnectType::COMMON => Ok(None),
-        }
+        client_flight::<Option<ElinkLoginInfo>>(self)
+            .await
+            .run(|| universal_login(self))
+            .await
     }
 
     async fn sso_service_login(&self, service: impl Into<String>) -> Result<Response> {
         service_sso_login(self.clone(), service).await
     }
-}
 
-async fn universal_sso_login(client: impl Client + Clone + Send) -> Result<SSOUniversalLoginInfo> {
-    let response = client.reqwest_client().get(ROOT_SSO_LOGIN).send().await?;
-    let status = response.status();
-    // use webvpn
-    if status == StatusCode::FOUND {
-        // redirect to webvpn root
-        // recursion to get the login page
-        let location = response
-            .headers()
-            .get("location")
-            .context("No location header in initial response")?
-            .to_str()
-            .context("Invalid location header")?;
-        let response = recursion_redirect_handle(client.clone(), location).await?;
+    async fn sso_login_outcome(&self, service: impl Into<String>) -> Result<SSOLoginOutcome> {
+        service_login_outcome(self.clone(), service).await
+    }
 
-        let url = response.url().clone();
-        let dom = response.text().await?;
-        let mut form = parse_hidden_values(dom.as_str())?;
+    async fn sso_send_verification_code(&self, verification: &SSOVerification) -> Result<()> {
+        let url = verification
+            .send_url
+            .clone()
+            .ok_or_else(|| Error::InvalidInput("The verification page can't send a code".into()))?;
+        // a POST, never retried so the user doesn't get the code twice
+        self.reqwest_client()
+            .post(url)
+            .form(&verification.form)
+            .headers(self.headers())
+            .send_with(self)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
 
-        let account = client.account();
-        form.insert("username".into(), account.user);
-        form.insert("password".into(), BASE64_STANDARD.encode(account.password));
+    async fn sso_verify(&self, verification: &SSOVerification, code: &str) -> Result<Response> {
+        let mut form = verification.form.clone();
+        form.insert(verification.field.clone(), code.to_string());
+        let response = self
+            .reqwest_client()
+            .post(verification.url.clone())
+            .form(&form)
+            .headers(self.headers())
+            .send_with(self)
+            .await?;
+        let response = finish_login(self.clone(), response).await?;
+        // as after a login without verification
+        self.properties()
+            .write()
+            .await
+            .insert_persistent(&verification.connect_type)?;
+        Ok(response)
+    }
 
-        let response = client.reqwest_client().post(url).form(&form).send().await?;
+    async fn sso_logout(&self) -> Result<()> {
+        let connect_type = {
+            let properties = self.properties();
+            let mut properties = properties.write().await;
+            let connect_type = properties.get_persistent::<SSOLoginConnectType>();
+            properties.remove_persistent::<SSOLoginConnectType>();
+            connect_type
+        };
+        let endpoints = self.endpoints();
+        match connect_type {
+            // the SSO can't be reached directly, its session ends with the WebVPN one
+            Ok(Some(SSOLoginConnectType::WEBVPN)) => {
+                let sso = endpoints.sso_url()?;
+                let result = end_session(self, endpoints.vpn_logout(), &endpoints.vpn_url()?).await;
+                self.cookies().lock().unwrap().remove_cookies(&sso);
+                result
+            }
+            _ => end_session(self, endpoints.sso_logout(), &endpoints.sso_url()?).await,
+        }
+    }
+}
 
-        let redirect_location = response
-            .headers()
-            .get("location")
-            .context("No redirect location after login")?
-            .to_str()
-            .context("Invalid redirect location")?;
+/// One login of [`SSOUniversalLogin::sso_universal_login`].
+async fn universal_login(client: &(impl Client + Clone + Send)) -> Result<Option<ElinkLoginInfo>> {
+    let login = universal_sso_login(client.clone()).await?;
+    client
+        .properties()
+        .write()
+        .await
+        .insert_persistent(&login.login_connect_type)?;
 
-        let response = client
-            .reqwest_client()
-            .get(redirect_location)
-            .headers(DEFAULT_HEADERS.clone())
-            .send()
-            .await?;
+    match login.login_connect_type {
+        SSOLoginConnectType::WEBVPN => {
+            let cookie = login
+                .response
+                .cookies()
+                .find(|cookie| cookie.name() == "clientInfo")
+                .ok_or_parse("webvpn clientInfo", "Get `EnlinkLoginInfo` Failed")?;
+            let decoded = BASE64_STANDARD.decode(cookie.value()).map_err(|e| {
+                Error::parse_with("webvpn clientInfo", "Decode clientInfo failed", e)
+            })?;
+            let json = String::from_utf8(decoded).map_err(|e| {
+                Error::parse_with("webvpn clientInfo", "Invalid UTF-8 in clientInfo", e)
+            })?;
+            Ok(Some(serde_json::from_str(&json)?))
+        }
+        SSOLoginConnectType::COMMON => Ok(None),
+    }
+}
 
-        client
-            .cookies()
-            .lock()
-            .unwrap()
-            .add_reqwest_cookies(response.cookies(), &ROOT_VPN_URL);
-        Ok(SSOUniversalLoginInfo {
-            response,
-            login_connect_type: SSOLoginConnectType::WEBVPN,
-        })
+/// Log in the way the [`crate::impls::network::NetworkDetector`] found,
+/// a network error probes again and logs in the new way if the path changed.
+async fn universal_sso_login(client: impl Client + Clone + Send) -> Result<SSOUniversalLoginInfo> {
+    let detected = client.network_environment().await.connect_type();
+    match sso_login_through(&client, detected.clone()).await {
+        Err(Error::Network(error)) => match client.reprobe_network().await.connect_type() {
+            Some(connect_type) if Some(&connect_type) != detected.as_ref() => {
+                sso_login_through(&client, Some(connect_type)).await
+            }
+            _ => Err(Error::Network(error)),
+        },
+        result => result,
     }
-    // connect `cczu` and don't need to redirect
-    else if status == StatusCode::OK {
-        Ok(SSOUniversalLoginInfo {
-            response: service_sso_login(client, "").await?,
+}
+
+/// `None` if nothing answered the probes, the SSO is asked which way to go.
+async fn sso_login_through(
+    client: &(impl Client + Clone + Send),
+    connect_type: Option<SSOLoginConnectType>,
+) -> Result<SSOUniversalLoginInfo> {
+    let connect_type = match connect_type {
+        Some(connect_type) => connect_type,
+        None => match client
+            .reqwest_client()
+            .get(client.endpoints().sso_login())
+            .send_with(client)
+            .await?
+            .status()
+        {
+            StatusCode::OK => SSOLoginConnectType::COMMON,
+            StatusCode::FOUND => SSOLoginConnectType::WEBVPN,
+            status => return Err(Error::UpstreamStatus(status)),
+        },
+    };
+    match connect_type {
+        SSOLoginConnectType::WEBVPN => webvpn_sso_login(client.clone()).await,
+        // connect `cczu` and don't need to redirect
+        SSOLoginConnectType::COMMON => Ok(SSOUniversalLoginInfo {
+            response: service_sso_login(client.clone(), "").await?,
             login_connect_type: SSOLoginConnectType::COMMON,
-        })
-    } else {
-        bail!("Login Failed with status: {}", status)
+        }),
     }
 }
 
+/// The SSO proxied by the WebVPN, from the login page of the portal.
+async fn webvpn_sso_login(client: impl Client + Clone + Send) -> Result<SSOUniversalLoginInfo> {
+    // follow the redirects to get the login page
+    let response = follow_get(
+        &client,
+        &format!("{}/enlink/sso/login", client.endpoints().vpn),
+    )
+    .await?
+    .response;
+    let url = response.url().clone();
+    let dom = response.text().await?;
+    let response = post_login_form(&client, url, dom).await?;
+
+    if response.status() != StatusCode::FOUND {
+        let url = response.url().clone();
+        let html = response.text().await?;
+        let mut outcome = parse_login_outcome(&html, &url);
+        if let SSOLoginOutcome::VerificationRequired(verification) = &mut outcome {
+            verification.connect_type = SSOLoginConnectType::WEBVPN;
+        }
+        outcome.into_result()?;
+        return Err(Error::AuthenticationFailed(
+            "SSO did not redirect after login".into(),
+        ));
+    }
+    let redirect_location = response
+        .headers()
+        .get(LOCATION)
+        .ok_or_parse("sso login", "No location header after login")?
+        .to_str()
+        .map_err(|e| Error::parse_with("sso login", "Invalid redirect location", e))?;
+
+    let response = client
+        .reqwest_client()
+        .get(redirect_location)
+        .headers(client.headers())
+        .send_with(&client)
+        .await?;
+
+    client
+        .cookies()
+        .lock()
+        .unwrap()
+        .add_reqwest_cookies(response.cookies(), &client.endpoints().vpn_url()?);
+    Ok(SSOUniversalLoginInfo {
+        response,
+        login_connect_type: SSOLoginConnectType::WEBVPN,
+    })
+}
+
 async fn service_sso_login(
     client: impl Client + Clone + Send,
     service: impl Into<String>,
 ) -> Result<Response> {
-    let api = format!("{}?service={}", ROOT_SSO_LOGIN, service.into());
-    let response = client.reqwest_client().get(api.clone()).send().await?;
+    let response = submit_login_form(&client, service.into()).await?;
+    finish_login(client, response).await
+}
 
-    // Has Logined before
+/// Follow the redirect to the service after the login,
+/// or diagnose the page the SSO answered with.
+async fn finish_login(client: impl Client + Clone + Send, response: Response) -> Result<Response> {
     if response.status() == StatusCode::FOUND {
-        let location = response
-            .headers()
-            .get(LOCATION)
-            .context("Get Location Failed")?
-            .to_str()
-            .context("Invalid location header")?;
-        return Ok(recursion_redirect_handle(client, location).await?);
+        follow_response(&client, Method::POST, response)
+            .await
+            .map(|redirected| redirected.response)
+    } else {
+        // the CAS renders the form again with the reason instead of redirecting
+        let (body, response) = buffer_response(response).await?;
+        parse_login_outcome(&String::from_utf8_lossy(&body), response.url()).into_result()?;
+        Ok(response)
     }
+}
 
-    let dom = response.text().await?;
-    let mut login_param = parse_hidden_values(dom.as_str())?;
-    let account = client.account();
-    login_param.insert("username".into(), account.user);
-    login_param.insert("password".into(), BASE64_STANDARD.encode(account.password));
+async fn service_login_outcome(
+    client: impl Client + Clone + Send,
+    service: impl Into<String>,
+) -> Result<SSOLoginOutcome> {
+    let response = submit_login_form(&client, service.into()).await?;
 
+    if response.status() == StatusCode::FOUND {
+        let location = redirect_location(&response)?;
+        Ok(SSOLoginOutcome::Success {
+            ticket: parse_service_ticket(location),
+        })
+    } else {
+        let url = response.url().clone();
+        Ok(parse_login_outcome(&response.text().await?, &url))
+    }
+}
+
+/// Post the login form of the service, a redirect means logged in before or just now.
+async fn submit_login_form(client: &(impl Client + Send), service: String) -> Result<Response> {
+    let api = format!("{}?service={}", client.endpoints().sso_login(), service);
     let response = client
         .reqwest_client()
-        .post(api)
-        .form(&login_param)
-        .headers(DEFAULT_HEADERS.clone())
-        .send()
+        .get(api.clone())
+        .send_with(client)
         .await?;
 
+    // Has Logined before
     if response.status() == StatusCode::FOUND {
-        let location = response
-            .headers()
-            .get(LOCATION)
-            .context("Get Location Failed")?
-            .to_str()
-            .context("Invalid location header")?;
-        Ok(recursion_redirect_handle(client, location).await?)
+        return Ok(response);
+    }
+
+    let url = response.url().clone();
+    let dom = response.text().await?;
+    post_login_form(client, url, dom).await
+}
+
+/// Fill the login form with the account and post it,
+/// solve the captcha again while the SSO keeps asking for one.
+async fn post_login_form(
+    client: &(impl Client + Send),
+    url: Url,
+    html: String,
+) -> Result<Response> {
+    let (mut url, mut html) = (url, html);
+    let mut attempt = 1;
+    loop {
+        let form = fill_login_form(client, &url, &html).await?;
+        let response = client
+            .reqwest_client()
+            .post(url)
+            .form(&form)
+            .headers(client.headers())
+            .send_with(client)
+            .await?;
+        if response.status() == StatusCode::FOUND
+            || client.captcha_solver().is_none()
+            || attempt >= CAPTCHA_ATTEMPTS
+        {
+            return Ok(response);
+        }
+
+        let (body, response) = buffer_response(response).await?;
+        let page = String::from_utf8_lossy(&body).into_owned();
+        if !matches!(
+            parse_login_outcome(&page, response.url()),
+            SSOLoginOutcome::CaptchaRequired(_)
+        ) {
+            return Ok(response);
+        }
+        url = response.url().clone();
+        html = page;
+        attempt += 1;
+    }
+}
+
+async fn fill_login_form(
+    client: &(impl Client + Send),
+    url: &Url,
+    html: &str,
+) -> Result<HashMap<String, Zeroizing<String>>> {
+    let mut form: HashMap<String, Zeroizing<String>> = parse_hidden_values(html)?
+        .into_iter()
+        .map(|(name, value)| (name, Zeroizing::new(value)))
+        .collect();
+    if let Some((field, image)) = parse_captcha(html) {
+        let solver = client
+            .captcha_solver()
+            .ok_or_else(|| Error::CaptchaRequired("No CaptchaSolver configured".into()))?;
+        // the default image of the CAS, next to the login page
+        let image_url = url
+            .join(image.as_deref().unwrap_or("captcha.html"))
+            .map_err(|e| Error::parse_with("sso login", "Invalid captcha url", e))?;
+        let image = client
+            .reqwest_client()
+            .get(image_url)
+            .headers(client.headers())
+            .send_with(client)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        form.insert(field, Zeroizing::new(solver.solve(&image).await?));
+    }
+
+    let password = client.password().await?;
+    form.insert("username".into(), Zeroizing::new(client.account().user));
+    form.insert(
+        "password".into(),
+        Zeroizing::new(BASE64_STANDARD.encode(password.expose())),
+    );
+    Ok(form)
+}
+
+/// The name of the captcha input and the url of its image.
+fn parse_captcha(html: &str) -> Option<(String, Option<String>)> {
+    let dom = Html::parse_document(html);
+    let input_selector = Selector::parse(
+        r#"input[name="captcha"], input[name="captchaResponse"], input[name="authcode"]"#,
+    )
+    .unwrap();
+    let image_selector = Selector::parse(r#"img[id^="captcha"], img[src*="captcha"]"#).unwrap();
+    let field = dom
+        .select(&input_selector)
+        .next()?
+        .attr("name")?
+        .to_string();
+    let image = dom
+        .select(&image_selector)
+        .next()
+        .and_then(|image| image.attr("src"))
+        .map(str::to_string);
+    Some((field, image))
+}
+
+fn redirect_location(response: &Response) -> Result<&str> {
+    response
+        .headers()
+        .get(LOCATION)
+        .ok_or_parse("sso login", "Get Location Failed")?
+        .to_str()
+        .map_err(|e| Error::parse_with("sso login", "Invalid location header", e))
+}
+
+/// Diagnose the page at `url` the SSO answered to the login form with.
+///
+/// A page other than the login form is a success,
+/// the login form again is a failure classified by its error message.
+pub fn parse_login_outcome(html: &str, url: &Url) -> SSOLoginOutcome {
+    let dom = Html::parse_document(html);
+    let error_selector =
+        Selector::parse("#errorMsg, #msg, #showErrorTip, .errors, .login-error, .auth_error")
+            .unwrap();
+    let message = dom
+        .select(&error_selector)
+        .map(|element| element.text().collect::<String>().trim().to_string())
+        .find(|message| !message.is_empty());
+
+    if let Some(mut verification) = parse_verification(&dom, url) {
+        verification.message = message;
+        return SSOLoginOutcome::VerificationRequired(Box::new(verification));
+    }
+
+    let Some(message) = message else {
+        return match (is_sso_login_page(html), parse_captcha(html)) {
+            (true, Some(_)) => SSOLoginOutcome::CaptchaRequired("Captcha required".into()),
+            (true, None) => SSOLoginOutcome::InvalidCredentials("Got the login form again".into()),
+            (false, _) => SSOLoginOutcome::Success { ticket: None },
+        };
+    };
+    let contains = |keywords: &[&str]| {
+        let lowercase = message.to_lowercase();
+        keywords.iter().any(|keyword| lowercase.contains(keyword))
+    };
+    if contains(&["验证码", "captcha"]) {
+        SSOLoginOutcome::CaptchaRequired(message)
+    } else if contains(&["锁定", "冻结", "locked", "disabled"]) {
+        SSOLoginOutcome::AccountLocked(message)
+    } else if contains(&["过期", "修改密码", "expired"]) {
+        SSOLoginOutcome::PasswordExpired(message)
     } else {
-        Ok(response)
+        SSOLoginOutcome::InvalidCredentials(message)
     }
 }
 
+/// The form asking for the second factor, without its error message.
+fn parse_verification(dom: &Html, url: &Url) -> Option<SSOVerification> {
+    let code_selector = Selector::parse(
+        r#"input[name="dynamicCode"], input[name="smsCode"], input[name="emailCode"], input[name="otpCode"]"#,
+    )
+    .unwrap();
+    let form_selector = Selector::parse("form").unwrap();
+    let hidden_selector = Selector::parse(r#"input[type="hidden"]"#).unwrap();
+    let target_selector = Selector::parse("#mobile, #phone, #email, .verify-target").unwrap();
+    let send_selector = Selector::parse("#sendCode, #getDynamicCode, [data-send-url]").unwrap();
+
+    let field = dom.select(&code_selector).next()?.attr("name")?.to_string();
+    let channel = match field.as_str() {
+        "emailCode" => VerificationChannel::Email,
+        "otpCode" => VerificationChannel::Otp,
+        _ => VerificationChannel::Sms,
+    };
+    let form = dom.select(&form_selector).next();
+    let action = form
+        .and_then(|form| form.attr("action"))
+        .and_then(|action| url.join(action).ok())
+        .unwrap_or_else(|| url.clone());
+    let hidden = form
+        .map(|form| {
+            form.select(&hidden_selector)
+                .filter_map(|input| Some((input.attr("name")?.into(), input.attr("value")?.into())))
+                .collect()
+        })
+        .unwrap_or_default();
+    let target = dom
+        .select(&target_selector)
+        .map(|element| element.text().collect::<String>().trim().to_string())
+        .find(|target| !target.is_empty());
+    let send_url = dom.select(&send_selector).next().and_then(|element| {
+        let href = ["data-send-url", "data-url", "href"]
+            .into_iter()
+            .find_map(|name| element.attr(name))?;
+        url.join(href).ok()
+    });
+
+    Some(SSOVerification {
+        channel,
+        target,
+        message: None,
+        url: action,
+        send_url,
+        field,
+        form: hidden,
+        connect_type: SSOLoginConnectType::COMMON,
+    })
+}
+
+/// The service ticket of the redirect after a successful login.
+pub fn parse_service_ticket(location: &str) -> Option<String> {
+    Url::parse(location)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == "ticket")
+        .map(|(_, ticket)| ticket.into_owned())
+}
+
 pub fn parse_hidden_values(html: &str) -> Result<HashMap<String, String>> {
     let mut hidden_values = HashMap::new();
     let dom = Html::parse_document(html);
@@ -170,12 +538,171 @@ pub fn parse_hidden_values(html: &str) -> Result<HashMap<String, String>> {
     for tag_hidden in tags_hidden {
         let name = tag_hidden
             .attr("name")
-            .context("Hidden input missing name attribute")?;
+            .ok_or_parse("hidden input", "Hidden input missing name attribute")?;
         let value = tag_hidden
             .attr("value")
-            .context("Hidden input missing value attribute")?;
+            .ok_or_parse("hidden input", "Hidden input missing value attribute")?;
         hidden_values.insert(name.to_string(), value.to_string());
     }
 
     Ok(hidden_values)
 }
+
+#[cfg(test)]
+mod test {
+    use reqwest::{Method, Url};
+
+    use super::{SSOUniversalLogin, parse_login_outcome, parse_service_ticket};
+    use crate::{
+        base::{client::Account, endpoints::Endpoints},
+        error::Error,
+        impls::{
+            client::DefaultClient,
+            login::sso_type::{SSOLoginOutcome, VerificationChannel},
+            transport::{MemoryTransport, MockResponse},
+        },
+    };
+
+    const LOGIN_FORM: &str = r#"<form id="fm1" method="post">
+        <input id="username" name="username" type="text"/>
+        <input id="password" name="password" type="password"/>
+        <input type="hidden" name="execution" value="e1s1"/>
+        {}
+    </form>"#;
+
+    fn login_page(extra: &str) -> String {
+        LOGIN_FORM.replace("{}", extra)
+    }
+
+    #[tokio::test]
+    async fn service_login_follows_redirects() {
+        let login = "http://cas.test/sso/login?service=http://app.test/";
+        let transport = MemoryTransport::new()
+            .route(
+                Method::GET,
+                login,
+                MockResponse::ok(
+                    r#"<form><input type="hidden" name="execution" value="e1s1"/></form>"#,
+                ),
+            )
+            .route(
+                Method::POST,
+                login,
+                MockResponse::redirect("http://app.test/?ticket=ST-1"),
+            )
+            .route(
+                Method::GET,
+                "http://app.test/?ticket=ST-1",
+                MockResponse::ok("welcome"),
+            );
+        let client = DefaultClient::builder()
+            .account(Account::new("user", "password"))
+            .endpoints(Endpoints::single_host("http://cas.test"))
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        let response = client.sso_service_login("http://app.test/").await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "welcome");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        let form = requests[1].body_text().unwrap();
+        assert!(form.contains("execution=e1s1"));
+        assert!(form.contains("username=user"));
+        assert!(form.contains("password=cGFzc3dvcmQ%3D"));
+    }
+
+    #[test]
+    fn login_outcomes() {
+        let url = Url::parse("http://cas.test/sso/login").unwrap();
+        let outcome = |extra: &str| parse_login_outcome(&login_page(extra), &url);
+        assert_eq!(
+            outcome(r#"<span id="errorMsg">用户名或密码错误</span>"#),
+            SSOLoginOutcome::InvalidCredentials("用户名或密码错误".into())
+        );
+        assert!(matches!(
+            outcome(r#"<div id="msg">账号已被锁定，请30分钟后再试</div>"#),
+            SSOLoginOutcome::AccountLocked(_)
+        ));
+        assert!(matches!(
+            outcome(r#"<span id="errorMsg">请输入验证码</span>"#),
+            SSOLoginOutcome::CaptchaRequired(_)
+        ));
+        assert!(matches!(
+            outcome(r#"<input name="captcha" type="text"/><img id="captchaImg"/>"#),
+            SSOLoginOutcome::CaptchaRequired(_)
+        ));
+        assert!(matches!(
+            outcome(r#"<span id="errorMsg">密码已过期，请修改密码</span>"#),
+            SSOLoginOutcome::PasswordExpired(_)
+        ));
+        assert!(matches!(
+            outcome(""),
+            SSOLoginOutcome::InvalidCredentials(_)
+        ));
+        assert_eq!(
+            parse_login_outcome("<html><body>登录成功</body></html>", &url),
+            SSOLoginOutcome::Success { ticket: None }
+        );
+
+        let verification = parse_login_outcome(
+            r#"<form method="post">
+                <span id="mobile">138****0000</span>
+                <input type="hidden" name="execution" value="e1s2"/>
+                <input name="dynamicCode" type="text"/>
+                <button id="sendCode" data-url="/sso/dynamicCode">发送</button>
+            </form>"#,
+            &url,
+        );
+        let SSOLoginOutcome::VerificationRequired(verification) = verification else {
+            panic!("not a verification: {:?}", verification);
+        };
+        assert_eq!(verification.channel, VerificationChannel::Sms);
+        assert_eq!(verification.target.as_deref(), Some("138****0000"));
+        assert_eq!(verification.url, url);
+        assert_eq!(
+            verification.send_url.unwrap().as_str(),
+            "http://cas.test/sso/dynamicCode"
+        );
+        assert_eq!(verification.form["execution"], "e1s2");
+        assert_eq!(
+            parse_service_ticket("http://app.test/?ticket=ST-1").as_deref(),
+            Some("ST-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn service_login_failures() {
+        let login = "http://cas.test/sso/login?service=http://app.test/";
+        let transport = MemoryTransport::new()
+            .route(Method::GET, login, MockResponse::ok(login_page("")))
+            .route(
+                Method::POST,
+                login,
+                MockResponse::ok(login_page(r#"<span id="errorMsg">账号已被锁定</span>"#)),
+            )
+            .route(
+                Method::POST,
+                login,
+                MockResponse::redirect("http://app.test/?ticket=ST-1"),
+            );
+        let client = DefaultClient::builder()
+            .account(Account::new("user", "password"))
+            .endpoints(Endpoints::single_host("http://cas.test"))
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            client.sso_service_login("http://app.test/").await,
+            Err(Error::AccountLocked(_))
+        ));
+        assert_eq!(
+            client.sso_login_outcome("http://app.test/").await.unwrap(),
+            SSOLoginOutcome::Success {
+                ticket: Some("ST-1".into())
+            }
+        );
+    }
+}
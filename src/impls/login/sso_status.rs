@@ -3,11 +3,24 @@ use std::future::Future;
 use reqwest::{StatusCode, header::LOCATION};
 
 use super::sso_type::SSOLoginConnectType;
-use crate::{base::client::Client, internals::fields::ROOT_SSO_LOGIN};
-use anyhow::{Result, bail};
+use crate::impls::{network::NetworkDetect, services::webvpn::WebVPNService};
+use crate::{
+    base::client::Client,
+    error::{Error, Result},
+    internals::{fields::ROOT_JWCAS, request::RequestBuilderExt},
+};
 
 pub trait SSOLoginStatus {
     fn sso_login_available(&self) -> impl Future<Output = bool>;
+    /// Like [`SSOLoginStatus::sso_login_available`],
+    /// but a request which couldn't be sent is an error rather than a dead session.
+    fn sso_session_alive(&self) -> impl Future<Output = Result<bool>>;
+    /// Whether the WebVPN session is alive,
+    /// the gateway redirects a proxied page to its login once the session is gone.
+    fn webvpn_login_available(&self) -> impl Future<Output = bool>;
+    /// Like [`SSOLoginStatus::webvpn_login_available`],
+    /// but a request which couldn't be sent is an error rather than a dead session.
+    fn webvpn_session_alive(&self) -> impl Future<Output = Result<bool>>;
     fn sso_login_connect_type(&self) -> impl Future<Output = Option<SSOLoginConnectType>>;
     fn sso_login_type(&self) -> impl Future<Output = Result<SSOLoginConnectType>>;
     fn sso_login_type_write(&self) -> impl Future<Output = Result<SSOLoginConnectType>>;
@@ -15,70 +28,94 @@ pub trait SSOLoginStatus {
 
 impl<C: Client> SSOLoginStatus for C {
     async fn sso_login_available(&self) -> bool {
-        if let Ok(response) = self
+        self.sso_session_alive().await.unwrap_or(false)
+    }
+
+    async fn sso_session_alive(&self) -> Result<bool> {
+        let response = self
             .reqwest_client()
             .get(format!(
-                "{}?service=http://ywtb.cczu.edu.cn/pc/index.html",
-                ROOT_SSO_LOGIN
+                "{}?service={}/pc/index.html",
+                self.endpoints().sso_login(),
+                self.endpoints().ywtb
             ))
-            .send()
-            .await
-        {
-            if response.status() == StatusCode::OK {
-                return false;
-            }
+            .send_with(self)
+            .await?;
+        if response.status() == StatusCode::FOUND {
+            return Ok(response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .is_some_and(|location| !location.contains("sso/login")));
+        }
+        Ok(false)
+    }
 
-            if response.status() == StatusCode::FOUND {
-                let location = response.headers().get(LOCATION).unwrap().to_str().unwrap();
-                if location.contains("sso/login") {
-                    return false;
-                }
-                return true;
-            }
+    async fn webvpn_login_available(&self) -> bool {
+        self.webvpn_session_alive().await.unwrap_or(false)
+    }
+
+    async fn webvpn_session_alive(&self) -> Result<bool> {
+        // the jwcas is always known, see `WebVpnUrlRewriter::fallback`
+        let rewriter = self.webvpn_url_rewriter().await;
+        let Some(url) = [self.endpoints().jwcas.as_str(), ROOT_JWCAS]
+            .iter()
+            .find_map(|root| rewriter.rewrite(&format!("{}/", root.trim_end_matches('/'))))
+        else {
+            return Ok(false);
+        };
+        let response = self.reqwest_client().get(url).send_with(self).await?;
+        if response.status().is_redirection() {
+            return Ok(!response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .is_some_and(|location| location.contains("sso/login")));
         }
-        false
+        Ok(response.status().is_success())
     }
 
     async fn sso_login_connect_type(&self) -> Option<SSOLoginConnectType> {
-        if let Some(property) = self
-            .properties()
+        self.properties()
             .read()
             .await
-            .get(SSOLoginConnectType::key())
-        {
-            Some(property.clone().into())
-        } else {
-            None
-        }
+            .get_persistent()
+            .ok()
+            .flatten()
     }
 
     async fn sso_login_type(&self) -> Result<SSOLoginConnectType> {
         if let Some(connect_type) = self.sso_login_connect_type().await {
             return Ok(connect_type);
         }
-
+        if let Some(connect_type) = self.network_environment().await.connect_type() {
+            return Ok(connect_type);
+        }
+        // offline, the request returns why
         match self
             .reqwest_client()
-            .get(ROOT_SSO_LOGIN)
-            .send()
+            .get(self.endpoints().sso_login())
+            .send_with(self)
             .await?
             .status()
         {
             StatusCode::OK => Ok(SSOLoginConnectType::COMMON),
             StatusCode::FOUND => Ok(SSOLoginConnectType::WEBVPN),
-            _ => bail!("Status Code Error"),
+            status => Err(Error::UpstreamStatus(status)),
         }
     }
 
+    /// Writing the type again is a no-op, the stored type is kept and returned.
     async fn sso_login_type_write(&self) -> Result<SSOLoginConnectType> {
         let connect = self.sso_login_type().await?;
         let locker = self.properties();
         let mut guard = locker.write().await;
-        if guard.contains_key(SSOLoginConnectType::key()) {
-            panic!("Can't write to existing property")
+        // another task may have written it meanwhile
+        if let Ok(Some(stored)) = guard.get_persistent::<SSOLoginConnectType>() {
+            return Ok(stored);
         }
 
-        guard.insert(SSOLoginConnectType::key(), connect.clone().into());
+        guard.insert_persistent(&connect)?;
 
         Ok(connect)
     }
@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 
-use reqwest::Response;
-use serde::Deserialize;
+use reqwest::{Response, Url};
+use serde::{Deserialize, Serialize};
 
-use crate::base::client::Property;
+use crate::{
+    base::properties::PersistentProperty,
+    error::{Error, Result},
+};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ElinkLoginInfo {
@@ -15,7 +18,8 @@ pub struct ElinkLoginInfo {
     pub loginkey: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SSOLoginConnectType {
     WEBVPN,
     COMMON,
@@ -23,43 +27,98 @@ pub enum SSOLoginConnectType {
 
 impl SSOLoginConnectType {
     #[inline(always)]
-    pub fn key() -> &'static str {
-        "login-connect-type"
+    pub const fn key() -> &'static str {
+        <Self as PersistentProperty>::KEY
     }
 }
 
-impl Into<Property> for SSOLoginConnectType {
-    fn into(self) -> Property {
-        Property::Str(match self {
-            SSOLoginConnectType::COMMON => "common",
-            SSOLoginConnectType::WEBVPN => "webvpn",
-        })
+impl PersistentProperty for SSOLoginConnectType {
+    const KEY: &'static str = "login-connect-type";
+}
+
+/// Parse the stored value, e.g. `"webvpn"` or `"common"`.
+impl TryFrom<&str> for SSOLoginConnectType {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "webvpn" => Ok(Self::WEBVPN),
+            "common" => Ok(Self::COMMON),
+            _ => Err(Error::InvalidInput(format!(
+                "Unknown login connect type `{}`",
+                value
+            ))),
+        }
     }
 }
 
-impl Into<SSOLoginConnectType> for Property {
-    fn into(self) -> SSOLoginConnectType {
+pub struct SSOUniversalLoginInfo {
+    pub response: Response,
+    pub login_connect_type: SSOLoginConnectType,
+}
+
+/// What the SSO answered to the submitted login form,
+/// see [`crate::impls::login::sso::parse_login_outcome`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SSOLoginOutcome {
+    /// Redirected to the service, with the service ticket if a service was given.
+    Success {
+        ticket: Option<String>,
+    },
+    InvalidCredentials(String),
+    AccountLocked(String),
+    CaptchaRequired(String),
+    PasswordExpired(String),
+    /// The password is correct, a code sent to the user is needed to continue.
+    VerificationRequired(Box<SSOVerification>),
+}
+
+impl SSOLoginOutcome {
+    /// The service ticket on success, the matching [`Error`] otherwise.
+    pub fn into_result(self) -> Result<Option<String>> {
         match self {
-            Property::Str(data) => match data {
-                "common" => SSOLoginConnectType::COMMON,
-                "webvpn" => SSOLoginConnectType::WEBVPN,
-                _ => panic!("Unknown Login Connect Type"),
-            },
-            _ => panic!("Wrong Property Type, expect `Property::String`"),
+            Self::Success { ticket } => Ok(ticket),
+            Self::InvalidCredentials(message) => Err(Error::AuthenticationFailed(message)),
+            Self::AccountLocked(message) => Err(Error::AccountLocked(message)),
+            Self::CaptchaRequired(message) => Err(Error::CaptchaRequired(message)),
+            Self::PasswordExpired(message) => Err(Error::PasswordExpired(message)),
+            Self::VerificationRequired(verification) => {
+                Err(Error::VerificationRequired(verification))
+            }
         }
     }
 }
 
-impl Into<SSOLoginConnectType> for HashMap<&'static str, Property> {
-    fn into(self) -> SSOLoginConnectType {
-        self.get(SSOLoginConnectType::key())
-            .expect("Can't get LoginConnectType! Need Login?")
-            .clone()
-            .into()
-    }
+/// Where the SSO sends the verification code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationChannel {
+    Sms,
+    Email,
+    /// A code generated by an authenticator app, nothing is sent.
+    Otp,
 }
 
-pub struct SSOUniversalLoginInfo {
-    pub response: Response,
-    pub login_connect_type: SSOLoginConnectType,
+/// A second factor demanded by the SSO after the password,
+/// continue with [`crate::impls::login::sso::SSOUniversalLogin::sso_verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SSOVerification {
+    pub channel: VerificationChannel,
+    /// Where the code goes as shown by the page, e.g. a masked phone number.
+    pub target: Option<String>,
+    /// Why the previous code was rejected.
+    pub message: Option<String>,
+    pub(crate) url: Url,
+    pub(crate) send_url: Option<Url>,
+    pub(crate) field: String,
+    pub(crate) form: HashMap<String, String>,
+    /// How the SSO was reached, stored once the code is accepted.
+    pub(crate) connect_type: SSOLoginConnectType,
+}
+
+impl SSOVerification {
+    /// Whether the code has to be requested with
+    /// [`crate::impls::login::sso::SSOUniversalLogin::sso_send_verification_code`].
+    pub fn can_send_code(&self) -> bool {
+        self.send_url.is_some()
+    }
 }
@@ -2,87 +2,128 @@ use std::collections::HashMap;
 
 use crate::{
     base::client::Client,
-    impls::login::sso_type::ElinkLoginInfo,
-    internals::fields::{DEFAULT_HEADERS, ROOT_VPN},
+    error::{Error, OptionExt, Result},
+    impls::login::sso_type::{ElinkLoginInfo, SSOLoginConnectType},
+    internals::{request::RequestBuilderExt, session::end_session},
 };
 use aes::{
     Aes128Enc,
     cipher::{BlockEncryptMut, KeyIvInit, block_padding::Pkcs7},
 };
-use anyhow::{Context, Result, bail};
 use base64::{Engine, prelude::BASE64_STANDARD};
 use cbc::Encryptor;
 use rand::Rng;
-use reqwest::{StatusCode, cookie::Cookie};
+use reqwest::StatusCode;
 
 pub type CbcAES128Enc = Encryptor<Aes128Enc>;
 
 pub trait WebVPNLogin {
     fn webvpn_login(&self) -> impl std::future::Future<Output = Result<ElinkLoginInfo>>;
+    /// End the WebVPN session and forget its cookies,
+    /// an SSO login made through the WebVPN ends too.
+    fn webvpn_logout(&self) -> impl std::future::Future<Output = Result<()>>;
 }
 
 impl<C: Client> WebVPNLogin for C {
     async fn webvpn_login(&self) -> Result<ElinkLoginInfo> {
-        let account = self.account();
-        let url = format!("{}/enlink/sso/login/submit", ROOT_VPN);
+        let root = &self.endpoints().vpn;
+        let url = format!("{}/enlink/sso/login/submit", root);
         const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
         let mut rng = rand::rng();
         let mut token = (0..16)
             .map(|_| {
                 let idx = rng.random_range(0..CHARSET.len());
-                CHARSET[idx] as u8
+                CHARSET[idx]
             })
             .collect::<Vec<u8>>();
         let iv = token.clone();
         token.reverse();
         let key = token.clone();
         let encryptor = CbcAES128Enc::new(key.as_slice().into(), iv.as_slice().into());
-        let pwd_clone = account.password;
-        let raw_pwd = pwd_clone.as_bytes();
+        let password = self.password().await?;
+        let raw_pwd = password.expose().as_bytes();
         let pwd_len = raw_pwd.len();
         let mut buf = [0u8; 256];
-        buf[..pwd_len].copy_from_slice(&raw_pwd);
+        if pwd_len >= buf.len() {
+            return Err(Error::InvalidInput("Password is too long".into()));
+        }
+        buf[..pwd_len].copy_from_slice(raw_pwd);
         let encrypt_buf = encryptor
             .encrypt_padded_mut::<Pkcs7>(&mut buf, pwd_len)
-            .map_err(|_| anyhow::anyhow!("Password encryption failed"))?;
+            .map_err(|_| Error::InvalidInput("Password encryption failed".into()))?;
         let encrypt_pwd = BASE64_STANDARD.encode(encrypt_buf);
         let mut data: HashMap<&'static str, String> = HashMap::new();
-        data.insert("username", account.user);
+        data.insert("username", self.account().user);
         data.insert("password", encrypt_pwd);
-        data.insert(
-            "token",
-            token.iter().map(|char| char.clone() as char).collect(),
-        );
+        data.insert("token", token.iter().map(|char| *char as char).collect());
         data.insert("language", "zh-CN,zh;q=0.9,en;q=0.8".into());
 
         let response = self
             .reqwest_client()
             .post(url)
-            .header("Refer", format!("{}/enlink/sso/login", ROOT_VPN))
-            .header("Origin", ROOT_VPN)
+            .header("Refer", format!("{}/enlink/sso/login", root))
+            .header("Origin", root)
             .header("Content-Type", "application/x-www-form-urlencoded")
-            .headers(DEFAULT_HEADERS.clone())
+            .headers(self.headers())
             .form(&data)
-            .send()
-            .await
-            .context("Failed to send login request")?;
+            .send_with(self)
+            .await?;
 
         if response.status() == StatusCode::FOUND {
-            let cookies: Vec<Cookie> = response
+            let cookie = response
                 .cookies()
-                .filter(|cookie| cookie.name() == "clientInfo")
-                .collect();
-            let cookie = cookies.first().context("No clientInfo cookie found")?;
+                .find(|cookie| cookie.name() == "clientInfo")
+                .ok_or_parse("webvpn clientInfo", "No clientInfo cookie found")?;
 
-            let decoded = BASE64_STANDARD
-                .decode(cookie.value())
-                .context("Failed to decode clientInfo cookie")?;
-            let json = String::from_utf8(decoded).context("Invalid UTF-8 in decoded cookie")?;
-            let elink_info: ElinkLoginInfo =
-                serde_json::from_str(&json).context("Failed to parse ElinkLoginInfo from JSON")?;
+            let decoded = BASE64_STANDARD.decode(cookie.value()).map_err(|e| {
+                Error::parse_with("webvpn clientInfo", "Failed to decode clientInfo cookie", e)
+            })?;
+            let json = String::from_utf8(decoded).map_err(|e| {
+                Error::parse_with("webvpn clientInfo", "Invalid UTF-8 in decoded cookie", e)
+            })?;
+            let elink_info: ElinkLoginInfo = serde_json::from_str(&json)?;
             Ok(elink_info)
         } else {
-            bail!("Login failed with status: {}", response.status())
+            Err(Error::AuthenticationFailed(format!(
+                "WebVPN login failed with status: {}",
+                response.status()
+            )))
         }
     }
+
+    async fn webvpn_logout(&self) -> Result<()> {
+        {
+            let properties = self.properties();
+            let mut properties = properties.write().await;
+            if let Ok(Some(SSOLoginConnectType::WEBVPN)) = properties.get_persistent() {
+                properties.remove_persistent::<SSOLoginConnectType>();
+            }
+        }
+        let endpoints = self.endpoints();
+        end_session(self, endpoints.vpn_logout(), &endpoints.vpn_url()?).await
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod test {
+    use super::WebVPNLogin;
+    use crate::{
+        impls::login::{sso::SSOUniversalLogin, sso_status::SSOLoginStatus},
+        mock::MockCampus,
+    };
+
+    #[tokio::test]
+    async fn logout() {
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let client = campus.client().unwrap();
+        client.sso_universal_login().await.unwrap();
+        let other = campus.client().unwrap();
+        other.webvpn_login().await.unwrap();
+
+        client.sso_logout().await.unwrap();
+        assert!(client.sso_login_connect_type().await.is_none());
+        other.webvpn_logout().await.unwrap();
+        assert!(other.sso_login_connect_type().await.is_none());
+        assert_eq!(campus.sso_sessions(), 0);
+    }
 }
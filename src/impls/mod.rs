@@ -1,4 +1,10 @@
 pub mod apps;
+pub mod builder;
 pub mod client;
+pub mod fixture;
+pub mod keepalive;
 pub mod login;
+pub mod network;
+pub mod pool;
 pub mod services;
+pub mod transport;
@@ -0,0 +1,352 @@
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+
+use crate::{
+    base::client::Client,
+    impls::login::{sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType},
+};
+
+/// Default of [`NetworkDetector::ttl`].
+pub const DEFAULT_NETWORK_TTL: Duration = Duration::from_secs(5 * 60);
+/// Default of [`NetworkDetector::probe_timeout`].
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How the campus systems are reached from here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkPath {
+    /// On the campus network, the SSO answers directly.
+    Campus,
+    /// Off campus, the SSO redirects to the WebVPN.
+    WebVpn,
+    /// Neither the SSO nor the WebVPN can be reached.
+    Offline,
+}
+
+/// What [`NetworkDetector`] found, see [`NetworkDetect::network_environment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkEnvironment {
+    pub path: NetworkPath,
+    /// The SSO login page answered without redirect.
+    pub sso_direct: bool,
+    /// The WebVPN portal answered.
+    pub webvpn: bool,
+    pub probed_at: Instant,
+}
+
+impl NetworkEnvironment {
+    /// The connect type a login would use, `None` if offline.
+    pub fn connect_type(&self) -> Option<SSOLoginConnectType> {
+        match self.path {
+            NetworkPath::Campus => Some(SSOLoginConnectType::COMMON),
+            NetworkPath::WebVpn => Some(SSOLoginConnectType::WEBVPN),
+            NetworkPath::Offline => None,
+        }
+    }
+}
+
+/// Probe the campus hosts in parallel and cache the result for [`Self::ttl`].
+///
+/// The iccard host is probed on its own by [`Self::iccard_lan`], a login never waits for it.
+///
+/// A client keeps one in its [`crate::base::properties::Properties`],
+/// see [`crate::impls::builder::ClientBuilder::network_detector`].
+#[derive(Debug)]
+pub struct NetworkDetector {
+    ttl: Duration,
+    probe_timeout: Duration,
+    cached: Mutex<Option<NetworkEnvironment>>,
+    /// Whether the iccard host answered and when.
+    iccard: Mutex<Option<(bool, Instant)>>,
+}
+
+impl Default for NetworkDetector {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_NETWORK_TTL,
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+            cached: Mutex::new(None),
+            iccard: Mutex::new(None),
+        }
+    }
+}
+
+impl NetworkDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long a probe result is used, default: [`DEFAULT_NETWORK_TTL`]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// A host which doesn't answer in time is unreachable, default: [`DEFAULT_PROBE_TIMEOUT`]
+    pub fn probe_timeout(mut self, timeout: Duration) -> Self {
+        self.probe_timeout = timeout;
+        self
+    }
+
+    /// The cached environment, probed again once expired.
+    ///
+    /// Concurrent callers wait for the same probe.
+    pub async fn environment(&self, client: &impl Client) -> NetworkEnvironment {
+        let mut cached = self.cached.lock().await;
+        if let Some(environment) = *cached
+            && environment.probed_at.elapsed() < self.ttl
+        {
+            return environment;
+        }
+        let environment = self.probe(client).await;
+        *cached = Some(environment);
+        environment
+    }
+
+    /// Probe now, whatever the age of the cached environment.
+    ///
+    /// The iccard host is probed again on the next [`Self::iccard_lan`].
+    pub async fn reprobe(&self, client: &impl Client) -> NetworkEnvironment {
+        *self.iccard.lock().await = None;
+        let mut cached = self.cached.lock().await;
+        let environment = self.probe(client).await;
+        *cached = Some(environment);
+        environment
+    }
+
+    /// Whether the iccard host answers, it's only served on the campus LAN.
+    ///
+    /// Cached for [`Self::ttl`] apart from [`Self::environment`].
+    pub async fn iccard_lan(&self, client: &impl Client) -> bool {
+        let mut cached = self.iccard.lock().await;
+        if let Some((reachable, probed_at)) = *cached
+            && probed_at.elapsed() < self.ttl
+        {
+            return reachable;
+        }
+        let reachable = self
+            .status(client, &client.endpoints().iccard)
+            .await
+            .is_some();
+        *cached = Some((reachable, Instant::now()));
+        reachable
+    }
+
+    pub async fn cached(&self) -> Option<NetworkEnvironment> {
+        *self.cached.lock().await
+    }
+
+    async fn probe(&self, client: &impl Client) -> NetworkEnvironment {
+        let endpoints = client.endpoints();
+        let sso = endpoints.sso_login();
+        let vpn = format!("{}/enlink/sso/login", endpoints.vpn);
+        let (sso, webvpn) = tokio::join!(self.status(client, &sso), self.status(client, &vpn));
+        let sso_direct = sso == Some(StatusCode::OK);
+        let webvpn = webvpn.is_some();
+        let path = match (sso_direct, webvpn) {
+            (true, _) => NetworkPath::Campus,
+            (false, true) => NetworkPath::WebVpn,
+            (false, false) => NetworkPath::Offline,
+        };
+        NetworkEnvironment {
+            path,
+            sso_direct,
+            webvpn,
+            probed_at: Instant::now(),
+        }
+    }
+
+    /// The status of a `GET`, `None` if nothing answered in time.
+    ///
+    /// Sent once through the transport, the retry policy would only delay the answer.
+    async fn status(&self, client: &impl Client, url: &str) -> Option<StatusCode> {
+        let request = client.reqwest_client().get(url).build().ok()?;
+        let response =
+            tokio::time::timeout(self.probe_timeout, client.transport().execute(request));
+        Some(response.await.ok()?.ok()?.status())
+    }
+}
+
+/// The [`NetworkDetector`] of a client, shared by its clones.
+pub trait NetworkDetect {
+    fn network_detector(&self) -> impl Future<Output = Arc<NetworkDetector>>;
+    /// The cached environment, probed on the first call and after the TTL.
+    fn network_environment(&self) -> impl Future<Output = NetworkEnvironment>;
+    /// Whether the iccard host answers, see [`NetworkDetector::iccard_lan`].
+    fn iccard_lan(&self) -> impl Future<Output = bool>;
+    /// Probe again, e.g. after requests start failing.
+    ///
+    /// If the path changed since the login, the stored [`SSOLoginConnectType`] is dropped,
+    /// so the next login and [`SSOLoginStatus::sso_login_type`] follow the new path.
+    fn reprobe_network(&self) -> impl Future<Output = NetworkEnvironment>;
+}
+
+impl<C: Client> NetworkDetect for C {
+    async fn network_detector(&self) -> Arc<NetworkDetector> {
+        let properties = self.properties();
+        if let Some(detector) = properties.read().await.get::<Arc<NetworkDetector>>() {
+            return detector.clone();
+        }
+        let mut properties = properties.write().await;
+        match properties.get::<Arc<NetworkDetector>>() {
+            Some(detector) => detector.clone(),
+            None => {
+                let detector = Arc::new(NetworkDetector::new());
+                properties.insert(detector.clone());
+                detector
+            }
+        }
+    }
+
+    async fn network_environment(&self) -> NetworkEnvironment {
+        self.network_detector().await.environment(self).await
+    }
+
+    async fn iccard_lan(&self) -> bool {
+        self.network_detector().await.iccard_lan(self).await
+    }
+
+    async fn reprobe_network(&self) -> NetworkEnvironment {
+        let environment = self.network_detector().await.reprobe(self).await;
+        if let Some(connect_type) = environment.connect_type()
+            && let Some(stored) = self.sso_login_connect_type().await
+            && stored != connect_type
+        {
+            self.properties()
+                .write()
+                .await
+                .remove_persistent::<SSOLoginConnectType>();
+        }
+        environment
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{NetworkDetect, NetworkDetector, NetworkEnvironment, NetworkPath};
+    use crate::{
+        base::{client::Client, endpoints::Endpoints},
+        impls::{
+            client::DefaultClient,
+            login::{
+                sso::SSOUniversalLogin, sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType,
+            },
+        },
+        mock::MockCampus,
+    };
+
+    #[tokio::test]
+    async fn detect_paths() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        let environment = client.network_environment().await;
+        assert_eq!(environment.path, NetworkPath::Campus);
+        assert!(environment.sso_direct);
+        assert!(client.iccard_lan().await);
+        // cached until the TTL
+        assert_eq!(client.clone().network_environment().await, environment);
+
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let client = campus.client().unwrap();
+        let environment = client.network_environment().await;
+        assert_eq!(environment.path, NetworkPath::WebVpn);
+        assert_eq!(
+            environment.connect_type(),
+            Some(SSOLoginConnectType::WEBVPN)
+        );
+        assert_eq!(
+            client.sso_login_type().await.unwrap(),
+            SSOLoginConnectType::WEBVPN
+        );
+
+        let client = DefaultClient::builder()
+            .endpoints(Endpoints::single_host("http://127.0.0.1:1"))
+            .network_detector(NetworkDetector::new().ttl(Duration::ZERO))
+            .build()
+            .unwrap();
+        let environment = client.network_environment().await;
+        assert_eq!(environment.path, NetworkPath::Offline);
+        assert!(!client.iccard_lan().await);
+        assert_ne!(
+            client.network_environment().await.probed_at,
+            environment.probed_at
+        );
+    }
+
+    #[tokio::test]
+    async fn environment_skips_iccard() {
+        let campus = MockCampus::start().await.unwrap();
+        // accepts the connection, never answers
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = campus
+            .client_builder()
+            .endpoints(Endpoints {
+                iccard: format!("http://{}", silent.local_addr().unwrap()),
+                ..campus.endpoints()
+            })
+            .network_detector(NetworkDetector::new().probe_timeout(Duration::from_secs(1)))
+            .build()
+            .unwrap();
+        let started = Instant::now();
+        assert_eq!(client.network_environment().await.path, NetworkPath::Campus);
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(!client.iccard_lan().await);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn reprobe_switches_routing() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        client
+            .properties()
+            .write()
+            .await
+            .insert_persistent(&SSOLoginConnectType::WEBVPN)
+            .unwrap();
+        assert_eq!(client.reprobe_network().await.path, NetworkPath::Campus);
+        assert!(client.sso_login_connect_type().await.is_none());
+        assert_eq!(
+            client.sso_login_type().await.unwrap(),
+            SSOLoginConnectType::COMMON
+        );
+    }
+
+    #[tokio::test]
+    async fn reprobe_after_network_error() {
+        let webvpn = MockCampus::builder().webvpn(true).start().await.unwrap();
+        // the SSO is gone since the cached probe, e.g. after leaving the campus
+        let client = webvpn
+            .client_builder()
+            .endpoints(Endpoints {
+                sso: "http://127.0.0.1:1".into(),
+                ..webvpn.endpoints()
+            })
+            .build()
+            .unwrap();
+        let stale = NetworkEnvironment {
+            path: NetworkPath::Campus,
+            sso_direct: true,
+            webvpn: true,
+            probed_at: Instant::now(),
+        };
+        *client.network_detector().await.cached.lock().await = Some(stale);
+
+        assert!(client.sso_universal_login().await.unwrap().is_some());
+        assert_eq!(
+            client.sso_login_connect_type().await,
+            Some(SSOLoginConnectType::WEBVPN)
+        );
+        assert_eq!(
+            client.network_detector().await.cached().await.unwrap().path,
+            NetworkPath::WebVpn
+        );
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    num::NonZeroUsize,
+    ops::Deref,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    base::client::Account,
+    error::{Error, Result},
+    impls::{builder::ClientBuilder, client::DefaultClient},
+    internals::single_flight::SingleFlight,
+};
+
+type LoginHook =
+    Arc<dyn Fn(DefaultClient) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+type EvictHook = Arc<dyn Fn(&str, EvictReason) + Send + Sync>;
+type ClientFactory = Arc<dyn Fn() -> ClientBuilder + Send + Sync>;
+type Pooled = (DefaultClient, Option<Arc<Semaphore>>);
+
+/// Why a client left the [`ClientPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictReason {
+    /// Older than [`ClientPoolBuilder::ttl`].
+    Expired,
+    /// Unused for longer than [`ClientPoolBuilder::idle_timeout`].
+    Idle,
+    /// Least recently used when the pool was full.
+    Capacity,
+    /// The account was acquired with another password.
+    Replaced,
+    /// Removed by [`ClientPool::remove`] or [`ClientPool::clear`].
+    Removed,
+}
+
+/// Counters of a [`ClientPool`], see [`ClientPool::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub login_failures: u64,
+}
+
+#[derive(Default)]
+struct Metrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    login_failures: AtomicU64,
+}
+
+struct Entry {
+    client: DefaultClient,
+    permits: Option<Arc<Semaphore>>,
+    created: Instant,
+    last_used: Instant,
+}
+
+/// Configure a [`ClientPool`].
+pub struct ClientPoolBuilder {
+    capacity: NonZeroUsize,
+    ttl: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    max_concurrency: Option<usize>,
+    factory: ClientFactory,
+    on_login: Option<LoginHook>,
+    on_evict: Option<EvictHook>,
+}
+
+impl Default for ClientPoolBuilder {
+    fn default() -> Self {
+        Self {
+            capacity: NonZeroUsize::new(16).unwrap(),
+            ttl: None,
+            idle_timeout: None,
+            max_concurrency: None,
+            factory: Arc::new(ClientBuilder::new),
+            on_login: None,
+            on_evict: None,
+        }
+    }
+}
+
+impl ClientPoolBuilder {
+    /// Default: `16`
+    pub fn capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Drop a client this long after it was created, e.g. before the SSO session expires.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Drop a client which hasn't been acquired for this long.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// How many [`PooledClient`] of one account may be held at the same time,
+    /// [`ClientPool::acquire`] waits for a free one.
+    pub fn max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = Some(max.max(1));
+        self
+    }
+
+    /// Build new clients from `factory` instead of [`ClientBuilder::new`],
+    /// the account is set by the pool.
+    pub fn client_builder(
+        mut self,
+        factory: impl Fn() -> ClientBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.factory = Arc::new(factory);
+        self
+    }
+
+    /// Run on every new client before it is pooled, e.g. to call `sso_universal_login`.
+    ///
+    /// The client is not pooled if the hook fails.
+    pub fn on_login<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(DefaultClient) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on_login = Some(Arc::new(move |client| Box::pin(hook(client))));
+        self
+    }
+
+    /// Called with the user of every client leaving the pool.
+    pub fn on_evict(mut self, hook: impl Fn(&str, EvictReason) + Send + Sync + 'static) -> Self {
+        self.on_evict = Some(Arc::new(hook));
+        self
+    }
+
+    pub fn build(self) -> ClientPool {
+        ClientPool {
+            entries: Arc::new(Mutex::new(LruCache::new(self.capacity))),
+            logins: Arc::default(),
+            metrics: Arc::new(Metrics::default()),
+            config: Arc::new(self),
+        }
+    }
+}
+
+/// Reuse logged in [`DefaultClient`]s per account.
+///
+/// Clients are keyed by [`Account::user`], acquiring a pooled user with
+/// another password replaces the client.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use cczuni::{base::client::Account, impls::pool::ClientPool};
+/// # use cczuni::impls::login::sso::SSOUniversalLogin;
+/// # async fn example() -> cczuni::Result<()> {
+/// let pool = ClientPool::builder()
+///     .ttl(Duration::from_secs(30 * 60))
+///     .max_concurrency(2)
+///     .on_login(|client| async move { client.sso_universal_login().await.map(|_| ()) })
+///     .build();
+/// let client = pool.acquire(Account::new("user", "password")).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ClientPool {
+    entries: Arc<Mutex<LruCache<String, Entry>>>,
+    /// The logins in progress per user, concurrent misses of a user share one.
+    logins: Arc<std::sync::Mutex<HashMap<String, Arc<SingleFlight<Pooled>>>>>,
+    metrics: Arc<Metrics>,
+    config: Arc<ClientPoolBuilder>,
+}
+
+impl Debug for ClientPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientPool")
+            .field("capacity", &self.config.capacity)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ClientPool {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl ClientPool {
+    pub fn builder() -> ClientPoolBuilder {
+        ClientPoolBuilder::default()
+    }
+
+    /// Return the pooled client of the account, or create, login and pool a new one.
+    pub async fn acquire(&self, account: Account) -> Result<PooledClient> {
+        let (client, permits) = match self.get(&account).await {
+            Some(pooled) => {
+                self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+                pooled
+            }
+            None => {
+                self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+                self.login(account).await?
+            }
+        };
+        let permit = match permits {
+            Some(permits) => Some(
+                permits
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| Error::InvalidInput("Client pool is closed".into()))?,
+            ),
+            None => None,
+        };
+        Ok(PooledClient {
+            client,
+            _permit: permit,
+        })
+    }
+
+    async fn get(&self, account: &Account) -> Option<Pooled> {
+        let mut entries = self.entries.lock().await;
+        self.evict_expired_locked(&mut entries);
+        if entries
+            .peek(&account.user)
+            .is_some_and(|entry| entry.client.account.password != account.password)
+        {
+            entries.pop(&account.user);
+            self.evicted(&account.user, EvictReason::Replaced);
+            return None;
+        }
+        let entry = entries.get_mut(&account.user)?;
+        entry.last_used = Instant::now();
+        Some((entry.client.clone(), entry.permits.clone()))
+    }
+
+    /// Create the client of a missed account, or join the login of the same user in progress.
+    async fn login(&self, account: Account) -> Result<Pooled> {
+        let flight = self
+            .logins
+            .lock()
+            .unwrap()
+            .entry(account.user.clone())
+            .or_default()
+            .clone();
+        let result = flight.run(|| self.create(account.clone())).await;
+        {
+            let mut logins = self.logins.lock().unwrap();
+            // the login is over, the first caller back removes it and the next miss starts another
+            if logins
+                .get(&account.user)
+                .is_some_and(|current| Arc::ptr_eq(current, &flight))
+            {
+                logins.remove(&account.user);
+            }
+        }
+        let (client, permits) = result?;
+        // joined the login of the same user with another password
+        if client.account.password != account.password {
+            return self.create(account).await;
+        }
+        Ok((client, permits))
+    }
+
+    async fn create(&self, account: Account) -> Result<Pooled> {
+        let client = (self.config.factory)().account(account).build()?;
+        // login outside of the lock, other accounts shouldn't wait for it
+        if let Some(login) = &self.config.on_login
+            && let Err(error) = login(client.clone()).await
+        {
+            self.metrics.login_failures.fetch_add(1, Ordering::Relaxed);
+            return Err(error);
+        }
+
+        let mut entries = self.entries.lock().await;
+        let user = client.account.user.clone();
+        // another task logged in the same account meanwhile
+        if let Some(entry) = entries
+            .get(&user)
+            .filter(|entry| entry.client.account.password == client.account.password)
+        {
+            return Ok((entry.client.clone(), entry.permits.clone()));
+        }
+
+        let now = Instant::now();
+        let permits = self
+            .config
+            .max_concurrency
+            .map(|max| Arc::new(Semaphore::new(max)));
+        let entry = Entry {
+            client: client.clone(),
+            permits: permits.clone(),
+            created: now,
+            last_used: now,
+        };
+        if let Some((evicted, _)) = entries.push(user.clone(), entry) {
+            let reason = match evicted == user {
+                true => EvictReason::Replaced,
+                false => EvictReason::Capacity,
+            };
+            self.evicted(&evicted, reason);
+        }
+        Ok((client, permits))
+    }
+
+    /// Remove the client of `user`, return whether it was pooled.
+    pub async fn remove(&self, user: &str) -> bool {
+        let removed = self.entries.lock().await.pop(user).is_some();
+        if removed {
+            self.evicted(user, EvictReason::Removed);
+        }
+        removed
+    }
+
+    pub async fn clear(&self) {
+        let mut entries = self.entries.lock().await;
+        while let Some((user, _)) = entries.pop_lru() {
+            self.evicted(&user, EvictReason::Removed);
+        }
+    }
+
+    /// Drop the clients which exceeded the ttl or idle timeout,
+    /// it also happens on every [`ClientPool::acquire`].
+    pub async fn evict_expired(&self) {
+        let mut entries = self.entries.lock().await;
+        self.evict_expired_locked(&mut entries);
+    }
+
+    fn evict_expired_locked(&self, entries: &mut LruCache<String, Entry>) {
+        let now = Instant::now();
+        let expired: Vec<(String, EvictReason)> = entries
+            .iter()
+            .filter_map(|(user, entry)| {
+                let reason = if self
+                    .config
+                    .ttl
+                    .is_some_and(|ttl| now.duration_since(entry.created) >= ttl)
+                {
+                    EvictReason::Expired
+                } else if self
+                    .config
+                    .idle_timeout
+                    .is_some_and(|idle| now.duration_since(entry.last_used) >= idle)
+                {
+                    EvictReason::Idle
+                } else {
+                    return None;
+                };
+                Some((user.clone(), reason))
+            })
+            .collect();
+        for (user, reason) in expired {
+            entries.pop(&user);
+            self.evicted(&user, reason);
+        }
+    }
+
+    fn evicted(&self, user: &str, reason: EvictReason) {
+        self.metrics.evictions.fetch_add(1, Ordering::Relaxed);
+        if let Some(hook) = &self.config.on_evict {
+            hook(user, reason);
+        }
+    }
+
+    pub async fn len(&self) -> usize {
+        self.entries.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.entries.lock().await.is_empty()
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.metrics.hits.load(Ordering::Relaxed),
+            misses: self.metrics.misses.load(Ordering::Relaxed),
+            evictions: self.metrics.evictions.load(Ordering::Relaxed),
+            login_failures: self.metrics.login_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A client acquired from a [`ClientPool`],
+/// holds one of the [`ClientPoolBuilder::max_concurrency`] slots of the account until dropped.
+#[derive(Debug)]
+pub struct PooledClient {
+    client: DefaultClient,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl PooledClient {
+    /// Take the client out, the slot is released.
+    pub fn into_inner(self) -> DefaultClient {
+        self.client
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = DefaultClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::{ClientPool, EvictReason};
+    use crate::{base::client::Account, error::Error};
+
+    #[tokio::test]
+    async fn reuse_and_evict() {
+        let logins = Arc::new(AtomicUsize::new(0));
+        let evicted = Arc::new(Mutex::new(vec![]));
+        let pool = {
+            let logins = logins.clone();
+            let evicted = evicted.clone();
+            ClientPool::builder()
+                .capacity(1.try_into().unwrap())
+                .on_login(move |_| {
+                    logins.fetch_add(1, Ordering::Relaxed);
+                    async { Ok(()) }
+                })
+                .on_evict(move |user, reason| {
+                    evicted.lock().unwrap().push((user.to_string(), reason))
+                })
+                .build()
+        };
+
+        pool.acquire(Account::new("a", "1")).await.unwrap();
+        pool.acquire(Account::new("a", "1")).await.unwrap();
+        pool.acquire(Account::new("a", "2")).await.unwrap();
+        pool.acquire(Account::new("b", "1")).await.unwrap();
+
+        assert_eq!(logins.load(Ordering::Relaxed), 3);
+        let stats = pool.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 3, 2));
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            vec![
+                ("a".to_string(), EvictReason::Replaced),
+                ("a".to_string(), EvictReason::Capacity)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn expire_and_failed_login() {
+        let pool = ClientPool::builder().ttl(Duration::ZERO).build();
+        pool.acquire(Account::new("a", "1")).await.unwrap();
+        pool.acquire(Account::new("a", "1")).await.unwrap();
+        assert_eq!(pool.stats().misses, 2);
+
+        let pool = ClientPool::builder()
+            .on_login(|_| async { Err(Error::AuthenticationFailed("wrong password".into())) })
+            .build();
+        assert!(pool.acquire(Account::new("a", "1")).await.is_err());
+        assert!(pool.is_empty().await);
+        assert_eq!(pool.stats().login_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn coalesce_logins() {
+        let logins = Arc::new(AtomicUsize::new(0));
+        let pool = {
+            let logins = logins.clone();
+            ClientPool::builder()
+                .on_login(move |_| {
+                    logins.fetch_add(1, Ordering::SeqCst);
+                    async {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok(())
+                    }
+                })
+                .build()
+        };
+        let (a, b, c) = tokio::join!(
+            pool.acquire(Account::new("a", "1")),
+            pool.acquire(Account::new("a", "1")),
+            pool.acquire(Account::new("b", "1")),
+        );
+        a.unwrap();
+        b.unwrap();
+        c.unwrap();
+        assert_eq!(logins.load(Ordering::SeqCst), 2);
+        assert!(pool.logins.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn clean_logins_across_threads() {
+        let pool = ClientPool::builder()
+            .on_login(|_| async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(())
+            })
+            .build();
+        let tasks: Vec<_> = (0..16)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move { pool.acquire(Account::new("a", "1")).await.map(drop) })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        assert!(pool.logins.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn limit_concurrency() {
+        let pool = ClientPool::builder().max_concurrency(1).build();
+        let held = pool.acquire(Account::new("a", "1")).await.unwrap();
+        let waiting = tokio::time::timeout(
+            Duration::from_millis(50),
+            pool.acquire(Account::new("a", "1")),
+        );
+        assert!(waiting.await.is_err());
+        drop(held);
+        pool.acquire(Account::new("a", "1")).await.unwrap();
+    }
+}
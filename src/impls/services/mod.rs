@@ -1,3 +1,8 @@
 pub mod sso_redirect;
 pub mod webvpn;
+pub mod webvpn_catalog;
+#[cfg(feature = "webvpn-proxy")]
+pub mod webvpn_proxy;
+pub mod webvpn_rewriter;
+pub mod webvpn_routes;
 pub mod webvpn_type;
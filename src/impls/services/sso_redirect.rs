@@ -1,16 +1,16 @@
 use std::{collections::HashMap, future::Future, sync::LazyLock};
 
-use reqwest::header::{COOKIE, HeaderMap};
+use reqwest::header::{COOKIE, HeaderMap, HeaderValue};
 
+use super::webvpn::WebVPNService;
 use crate::base::client::Client;
+use crate::error::{Error, Result};
 use crate::impls::login::{sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType};
-use crate::internals::fields::DEFAULT_HEADERS;
-use crate::internals::{
-    cookies_io::CookiesIOExt,
-    fields::{ROOT_SSO_URL, ROOT_VPN_URL},
-};
+use crate::internals::cookies_io::CookiesIOExt;
 
-pub const STATIC_SERVER_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+/// Fallback of [`super::webvpn_rewriter::WebVpnUrlRewriter`] for the default vpn root,
+/// before the services are fetched.
+pub static STATIC_SERVER_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     let mut map = HashMap::new();
 
     // jwcas
@@ -22,37 +22,94 @@ pub const STATIC_SERVER_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|
     map
 });
 
+/// Both return [`Error::NotLoggedIn`] before [`crate::impls::login::sso::SSOUniversalLogin::sso_universal_login`].
 pub trait SSORedirect {
-    fn sso_redirect(&self, url: impl Into<String>) -> impl Future<Output = String>;
-    fn sso_cookies_headers(&self) -> impl Future<Output = HeaderMap>;
+    fn sso_redirect(&self, url: impl Into<String>) -> impl Future<Output = Result<String>>;
+    fn sso_cookies_headers(&self) -> impl Future<Output = Result<HeaderMap>>;
 }
 
 impl<C: Client> SSORedirect for C {
-    async fn sso_redirect(&self, url: impl Into<String>) -> String {
+    async fn sso_redirect(&self, url: impl Into<String>) -> Result<String> {
         let url = url.into();
 
         match self
             .sso_login_connect_type()
             .await
-            .expect("Can't get login connect type! Need Login?")
+            .ok_or(Error::NotLoggedIn)?
         {
-            SSOLoginConnectType::WEBVPN => STATIC_SERVER_MAP.get(&url).unwrap_or(&url).to_string(),
-            SSOLoginConnectType::COMMON => url,
+            SSOLoginConnectType::WEBVPN => Ok(self
+                .webvpn_url_rewriter()
+                .await
+                .rewrite(&url)
+                .unwrap_or(url)),
+            SSOLoginConnectType::COMMON => Ok(url),
         }
     }
 
-    async fn sso_cookies_headers(&self) -> HeaderMap {
+    async fn sso_cookies_headers(&self) -> Result<HeaderMap> {
         let from = match self
             .sso_login_connect_type()
             .await
-            .expect("Can't get login connect type! Need Login?")
+            .ok_or(Error::NotLoggedIn)?
         {
-            SSOLoginConnectType::WEBVPN => &ROOT_VPN_URL,
-            SSOLoginConnectType::COMMON => &ROOT_SSO_URL,
-        };
+            SSOLoginConnectType::WEBVPN => self.endpoints().vpn_url(),
+            SSOLoginConnectType::COMMON => self.endpoints().sso_url(),
+        }?;
         let cookies = self.cookies().lock().unwrap().headers(&from);
-        let mut headers = DEFAULT_HEADERS.clone();
-        headers.insert(COOKIE, cookies.parse().unwrap());
-        headers
+        let mut headers = self.headers();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&cookies)
+                .map_err(|e| Error::parse_with("cookies", "Invalid cookie header", e))?,
+        );
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SSORedirect;
+    use crate::{
+        base::client::Client,
+        error::Error,
+        impls::{
+            client::DefaultClient,
+            login::{sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType},
+        },
+    };
+
+    #[tokio::test]
+    async fn not_logged_in() {
+        let client = DefaultClient::default();
+        assert!(matches!(
+            client.sso_redirect("http://219.230.159.132").await,
+            Err(Error::NotLoggedIn)
+        ));
+        assert!(matches!(
+            client.sso_cookies_headers().await,
+            Err(Error::NotLoggedIn)
+        ));
+
+        client
+            .properties()
+            .write()
+            .await
+            .insert_persistent(&SSOLoginConnectType::COMMON)
+            .unwrap();
+        assert_eq!(
+            client.sso_redirect("http://219.230.159.132").await.unwrap(),
+            "http://219.230.159.132"
+        );
+        assert!(client.sso_cookies_headers().await.is_ok());
+        // writing the stored type again is fine
+        assert_eq!(
+            client.sso_login_type_write().await.unwrap(),
+            SSOLoginConnectType::COMMON
+        );
+        assert_eq!(
+            SSOLoginConnectType::try_from("WebVPN").unwrap(),
+            SSOLoginConnectType::WEBVPN
+        );
+        assert!(SSOLoginConnectType::try_from("vpn").is_err());
     }
 }
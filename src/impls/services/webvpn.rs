@@ -1,14 +1,19 @@
 use std::{collections::HashMap, future::Future};
 
 use crate::{
-    base::client::Client,
-    internals::fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, ROOT_VPN},
+    base::{client::Client, properties::Properties},
+    error::Result,
+    internals::request::RequestBuilderExt,
 };
-use anyhow::Result;
 use reqwest::StatusCode;
 
-use super::webvpn_type::{
-    ElinkProxyData, ElinkServiceData, ElinkServiceInfoData, ElinkUserInfoData, Message,
+use super::{
+    webvpn_catalog::WebVpnCatalog,
+    webvpn_rewriter::WebVpnUrlRewriter,
+    webvpn_routes::WebVpnRoutes,
+    webvpn_type::{
+        ElinkProxyData, ElinkServiceData, ElinkServiceInfoData, ElinkUserInfoData, Message,
+    },
 };
 
 /// Must be used in WebVPN mode
@@ -25,18 +30,59 @@ pub trait WebVPNService {
         &self,
         user_id: impl Into<String>,
     ) -> impl Future<Output = Result<Message<ElinkServiceInfoData>>>;
+    /// The groups whose name contains `name_like`, with the services whose name contains
+    /// `service_name_like`, an empty filter keeps everything.
+    fn webvpn_search_tree_with_service(
+        &self,
+        user_id: impl Into<String>,
+        name_like: &str,
+        service_name_like: &str,
+    ) -> impl Future<Output = Result<Message<ElinkServiceInfoData>>>;
+    /// The hosts of the services are learned by [`Self::webvpn_url_rewriter`].
     fn webvpn_get_service_by_user(
         &self,
         user_id: impl Into<String>,
     ) -> impl Future<Output = Result<Message<Vec<ElinkServiceData>>>>;
+    /// The services whose name contains `name`, see [`Self::webvpn_get_service_by_user`].
+    fn webvpn_search_service_by_user(
+        &self,
+        user_id: impl Into<String>,
+        name: &str,
+    ) -> impl Future<Output = Result<Message<Vec<ElinkServiceData>>>>;
     fn webvpn_get_visit_service_by_user(
         &self,
         user_id: impl Into<String>,
     ) -> impl Future<Output = Result<Message<Vec<ElinkServiceData>>>>;
+    fn webvpn_search_visit_service_by_user(
+        &self,
+        user_id: impl Into<String>,
+        name: &str,
+    ) -> impl Future<Output = Result<Message<Vec<ElinkServiceData>>>>;
+    /// The tree, the services and the visited services merged, see [`WebVpnCatalog`].
+    fn webvpn_catalog(
+        &self,
+        user_id: impl Into<String>,
+    ) -> impl Future<Output = Result<WebVpnCatalog>>;
+    /// [`Self::webvpn_catalog`] of the services whose name contains `keyword`,
+    /// filtered by the server.
+    fn webvpn_search_catalog(
+        &self,
+        user_id: impl Into<String>,
+        keyword: &str,
+    ) -> impl Future<Output = Result<WebVpnCatalog>>;
     fn webvpn_get_proxy_service(
         &self,
         user_id: impl Into<String>,
     ) -> impl Future<Output = Result<Message<ElinkProxyData>>>;
+    /// The split routing of [`Self::webvpn_get_proxy_service`],
+    /// to export as a PAC file, a Clash rule set or a CIDR list.
+    fn webvpn_get_routes(
+        &self,
+        user_id: impl Into<String>,
+    ) -> impl Future<Output = Result<WebVpnRoutes>>;
+    /// The hosts learned from [`Self::webvpn_get_service_by_user`],
+    /// or [`WebVpnUrlRewriter::fallback`] before it's called.
+    fn webvpn_url_rewriter(&self) -> impl Future<Output = WebVpnUrlRewriter>;
 }
 
 impl<C: Client> WebVPNService for C {
@@ -48,11 +94,11 @@ impl<C: Client> WebVPNService for C {
             .reqwest_client()
             .get(format!(
                 "{}/enlink/api/client/user/findByUserId/{}",
-                ROOT_VPN,
+                self.endpoints().vpn,
                 user_id.into()
             ))
-            .headers(DEFAULT_HEADERS.clone())
-            .send()
+            .headers(self.headers())
+            .send_with(self)
             .await?;
         let json = response.text().await?;
         Ok(serde_json::from_str(&json)?)
@@ -61,23 +107,32 @@ impl<C: Client> WebVPNService for C {
     async fn webvpn_get_tree_with_service(
         &self,
         user_id: impl Into<String>,
+    ) -> Result<Message<ElinkServiceInfoData>> {
+        self.webvpn_search_tree_with_service(user_id, "", "").await
+    }
+
+    async fn webvpn_search_tree_with_service(
+        &self,
+        user_id: impl Into<String>,
+        name_like: &str,
+        service_name_like: &str,
     ) -> Result<Message<ElinkServiceInfoData>> {
         let mut body = HashMap::new();
-        body.insert("nameLike", "".to_string());
-        body.insert("serviceNameLike", "".to_string());
+        body.insert("nameLike", name_like.to_string());
+        body.insert("serviceNameLike", service_name_like.to_string());
         body.insert("userId", user_id.into());
         let response = self
             .reqwest_client()
             .post(format!(
                 "{}/enlink/api/client/service/group/treeWithService/",
-                ROOT_VPN
+                self.endpoints().vpn
             ))
-            .headers(DEFAULT_HEADERS.clone())
-            .header("Referer", format!("{}/enlink/", ROOT_VPN))
-            .header("Origin", ROOT_VPN)
+            .headers(self.headers())
+            .header("Referer", format!("{}/enlink/", self.endpoints().vpn))
+            .header("Origin", &self.endpoints().vpn)
             .header("Content-Type", "application/json;charset=utf-8")
             .body(serde_json::to_string(&body)?)
-            .send()
+            .send_idempotent_with(self)
             .await?;
         let json = response.text().await?;
         Ok(serde_json::from_str(&json)?)
@@ -86,43 +141,68 @@ impl<C: Client> WebVPNService for C {
     async fn webvpn_get_service_by_user(
         &self,
         user_id: impl Into<String>,
+    ) -> Result<Message<Vec<ElinkServiceData>>> {
+        self.webvpn_search_service_by_user(user_id, "").await
+    }
+
+    async fn webvpn_search_service_by_user(
+        &self,
+        user_id: impl Into<String>,
+        name: &str,
     ) -> Result<Message<Vec<ElinkServiceData>>> {
         let mut param = HashMap::new();
-        param.insert("name", "");
+        param.insert("name", name);
         let response = self
             .reqwest_client()
             .get(format!(
                 "{}/enlink/api/client/service/sucmp/findServiceByUserId/{}",
-                ROOT_VPN,
+                self.endpoints().vpn,
                 user_id.into()
             ))
-            .headers(DEFAULT_HEADERS.clone())
-            .header("Referer", format!("{}/enlink/", ROOT_VPN))
-            .header("Origin", ROOT_VPN)
+            .headers(self.headers())
+            .header("Referer", format!("{}/enlink/", self.endpoints().vpn))
+            .header("Origin", &self.endpoints().vpn)
             .query(&param)
-            .send()
+            .send_with(self)
             .await?;
         let json = response.text().await?;
-        Ok(serde_json::from_str(&json)?)
+        let services: Message<Vec<ElinkServiceData>> = serde_json::from_str(&json)?;
+
+        // read and write under one lock, concurrent calls keep each other's hosts
+        let properties = self.properties();
+        let mut properties = properties.write().await;
+        let mut rewriter = stored_rewriter(&properties, &self.endpoints().vpn);
+        if rewriter.learn_services(&services.data) > 0 {
+            properties.insert_persistent(&rewriter)?;
+        }
+        Ok(services)
     }
 
     async fn webvpn_get_visit_service_by_user(
         &self,
         user_id: impl Into<String>,
+    ) -> Result<Message<Vec<ElinkServiceData>>> {
+        self.webvpn_search_visit_service_by_user(user_id, "").await
+    }
+
+    async fn webvpn_search_visit_service_by_user(
+        &self,
+        user_id: impl Into<String>,
+        name: &str,
     ) -> Result<Message<Vec<ElinkServiceData>>> {
         let mut param = HashMap::new();
-        param.insert("name", "");
+        param.insert("name", name);
         let response = self
             .reqwest_client()
             .get(format!(
                 "{}/enlink/api/client/service/suvisitmp/findVisitServiceByUserId/{}",
-                ROOT_VPN,
+                self.endpoints().vpn,
                 user_id.into()
             ))
-            .headers(DEFAULT_HEADERS.clone())
-            .header("Referer", format!("{}/enlink/", ROOT_VPN))
+            .headers(self.headers())
+            .header("Referer", format!("{}/enlink/", self.endpoints().vpn))
             .query(&param)
-            .send()
+            .send_with(self)
             .await?;
         let json = response.text().await?;
         Ok(serde_json::from_str(&json)?)
@@ -130,7 +210,12 @@ impl<C: Client> WebVPNService for C {
 
     /// Client Redirect Policy: [`reqwest::redirect::Policy::none()`]
     async fn webvpn_available(&self) -> bool {
-        if let Ok(response) = self.reqwest_client().get(ROOT_SSO_LOGIN).send().await {
+        if let Ok(response) = self
+            .reqwest_client()
+            .get(self.endpoints().sso_login())
+            .send_with(self)
+            .await
+        {
             return response.status() == StatusCode::FOUND;
         }
         false
@@ -144,12 +229,129 @@ impl<C: Client> WebVPNService for C {
             .reqwest_client()
             .get(format!(
                 "{}/enlink/api/client/user/terminal/rules/{}",
-                ROOT_VPN,
+                self.endpoints().vpn,
                 user_id.into()
             ))
-            .send()
+            .send_with(self)
             .await?;
         let json = response.text().await?;
         Ok(serde_json::from_str(&json)?)
     }
+
+    async fn webvpn_get_routes(&self, user_id: impl Into<String>) -> Result<WebVpnRoutes> {
+        let message = self.webvpn_get_proxy_service(user_id).await?;
+        Ok(WebVpnRoutes::new(&message.data))
+    }
+
+    async fn webvpn_catalog(&self, user_id: impl Into<String>) -> Result<WebVpnCatalog> {
+        self.webvpn_search_catalog(user_id, "").await
+    }
+
+    async fn webvpn_search_catalog(
+        &self,
+        user_id: impl Into<String>,
+        keyword: &str,
+    ) -> Result<WebVpnCatalog> {
+        let user_id = user_id.into();
+        let (tree, services, visited) = tokio::try_join!(
+            self.webvpn_search_tree_with_service(&user_id, "", keyword),
+            self.webvpn_search_service_by_user(&user_id, keyword),
+            self.webvpn_search_visit_service_by_user(&user_id, keyword),
+        )?;
+        Ok(WebVpnCatalog::new(
+            &self.endpoints().vpn,
+            &tree.data,
+            &services.data,
+            &visited.data,
+        ))
+    }
+
+    async fn webvpn_url_rewriter(&self) -> WebVpnUrlRewriter {
+        stored_rewriter(&*self.properties().read().await, &self.endpoints().vpn)
+    }
+}
+
+fn stored_rewriter(properties: &Properties, vpn: &str) -> WebVpnUrlRewriter {
+    match properties.get_persistent() {
+        Ok(Some(rewriter)) => rewriter,
+        _ => WebVpnUrlRewriter::fallback(vpn),
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod test {
+    use super::WebVPNService;
+    use crate::{
+        impls::{
+            client::DefaultClient, login::sso::SSOUniversalLogin,
+            services::sso_redirect::SSORedirect,
+        },
+        mock::MockCampus,
+    };
+
+    /// A client logged in through the WebVPN, with its WebVPN user id.
+    async fn login(campus: &MockCampus) -> (DefaultClient, String) {
+        let client = campus.client().unwrap();
+        let info = client.sso_universal_login().await.unwrap().unwrap();
+        (client, info.userid)
+    }
+
+    #[tokio::test]
+    async fn routes() {
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let (client, user_id) = login(&campus).await;
+        let routes = client.webvpn_get_routes(&user_id).await.unwrap();
+        assert_eq!(routes.to_cidr_list(), "10.10.0.0/23\n219.230.159.0/24\n");
+        assert_eq!(
+            routes.dns,
+            ["202.195.100.1".parse::<std::net::IpAddr>().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn learn_services() {
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let (client, user_id) = login(&campus).await;
+        // unknown hosts aren't rewritten
+        assert!(
+            client
+                .sso_redirect("http://lib.cczu.edu.cn:8080")
+                .await
+                .unwrap()
+                .starts_with("http://lib")
+        );
+        let services = client.webvpn_get_service_by_user(&user_id).await.unwrap();
+        assert_eq!(services.data.len(), 2);
+        assert_eq!(
+            client
+                .sso_redirect("http://lib.cczu.edu.cn:8080")
+                .await
+                .unwrap(),
+            format!("{}/http/webvpn5f1e0b2a", campus.url())
+        );
+    }
+
+    #[tokio::test]
+    async fn catalog() {
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let (client, user_id) = login(&campus).await;
+        let catalog = client.webvpn_catalog(&user_id).await.unwrap();
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog.group(&["资源", "图书"])[0].service.id, "service-2");
+        assert_eq!(catalog.recently_visited()[0].service.id, "service-2");
+        assert_eq!(
+            catalog.launch_url("service-1").unwrap(),
+            format!(
+                "{}/http/webvpndc2d086cb5b297c15e661687e73c1549",
+                campus.url()
+            )
+        );
+
+        let catalog = client
+            .webvpn_search_catalog(&user_id, "教务")
+            .await
+            .unwrap();
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(catalog.get("service-1").unwrap().group_path, ["教学"]);
+    }
 }
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use super::webvpn_type::{ElinkServiceData, ElinkServiceInfoData};
+
+/// A service of [`WebVpnCatalog`] and where it is found.
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub service: ElinkServiceData,
+    /// The titles of the groups from below the root to the service, empty if it's in no group.
+    pub group_path: Vec<String>,
+    /// The url opening the service through the WebVPN, `None` if the host isn't known.
+    pub launch_url: Option<String>,
+}
+
+/// The services of a user, merged from the group tree and the flat lists,
+/// see [`super::webvpn::WebVPNService::webvpn_catalog`].
+///
+/// A service is listed once, at its first place in the tree.
+#[derive(Debug, Clone, Default)]
+pub struct WebVpnCatalog {
+    entries: Vec<CatalogEntry>,
+    by_id: HashMap<String, usize>,
+    /// Indexes of the visited services, most recent first.
+    visited: Vec<usize>,
+}
+
+impl WebVpnCatalog {
+    /// `visited` is in the order of the server, most recent first.
+    pub fn new(
+        vpn: &str,
+        tree: &ElinkServiceInfoData,
+        services: &[ElinkServiceData],
+        visited: &[ElinkServiceData],
+    ) -> Self {
+        let mut catalog = Self::default();
+        let vpn = vpn.trim_end_matches('/');
+        for service in &tree.service_list {
+            catalog.add(vpn, service, &[]);
+        }
+        catalog.walk(vpn, &tree.children, &mut vec![]);
+        for service in services.iter().chain(visited) {
+            catalog.add(vpn, service, &[]);
+        }
+        catalog.visited = visited
+            .iter()
+            .filter_map(|service| catalog.by_id.get(&service.id).copied())
+            .collect();
+        catalog
+    }
+
+    fn walk(&mut self, vpn: &str, groups: &[ElinkServiceInfoData], path: &mut Vec<String>) {
+        for group in groups {
+            path.push(group.title.clone());
+            let services = group
+                .service_list
+                .iter()
+                .chain(group.service_all_list.iter().flatten());
+            for service in services {
+                self.add(vpn, service, path);
+            }
+            self.walk(vpn, &group.children, path);
+            path.pop();
+        }
+    }
+
+    fn add(&mut self, vpn: &str, service: &ElinkServiceData, path: &[String]) {
+        if self.by_id.contains_key(&service.id) {
+            return;
+        }
+        self.by_id.insert(service.id.clone(), self.entries.len());
+        self.entries.push(CatalogEntry {
+            service: service.clone(),
+            group_path: path.to_vec(),
+            launch_url: launch_url(vpn, service),
+        });
+    }
+
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CatalogEntry> {
+        self.by_id.get(id).map(|index| &self.entries[*index])
+    }
+
+    /// The services whose name or description contains `keyword`, ignoring the case.
+    pub fn search(&self, keyword: &str) -> Vec<&CatalogEntry> {
+        let keyword = keyword.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.service.name.to_lowercase().contains(&keyword)
+                    || entry.service.description.to_lowercase().contains(&keyword)
+            })
+            .collect()
+    }
+
+    /// The services of the group at `path` and of its subgroups.
+    pub fn group(&self, path: &[&str]) -> Vec<&CatalogEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.group_path.len() >= path.len()
+                    && entry.group_path.iter().zip(path).all(|(a, b)| a == b)
+            })
+            .collect()
+    }
+
+    /// The visited services, most recent first.
+    pub fn recently_visited(&self) -> Vec<&CatalogEntry> {
+        self.visited
+            .iter()
+            .map(|index| &self.entries[*index])
+            .collect()
+    }
+
+    /// The launch url of the service `id`, see [`CatalogEntry::launch_url`].
+    pub fn launch_url(&self, id: &str) -> Option<&str> {
+        self.get(id)?.launch_url.as_deref()
+    }
+}
+
+/// The proxied `url_plus`, or else the server proxied with its `host_md5`.
+fn launch_url(vpn: &str, service: &ElinkServiceData) -> Option<String> {
+    if let Some(url) = service.url_plus.as_deref().filter(|url| !url.is_empty()) {
+        return Some(match url.starts_with('/') {
+            true => format!("{}{}", vpn, url),
+            false => url.to_string(),
+        });
+    }
+    let host_md5 = service.host_md5.as_deref().filter(|md5| !md5.is_empty())?;
+    let (scheme, rest) = match service.server.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+        None => (
+            service.type_of.to_ascii_lowercase(),
+            service.server.as_str(),
+        ),
+    };
+    let scheme = match scheme.as_str() {
+        "https" => "https",
+        _ => "http",
+    };
+    let path = rest
+        .find(['/', '?', '#'])
+        .map_or("", |index| &rest[index..]);
+    Some(format!("{}/{}/webvpn{}{}", vpn, scheme, host_md5, path))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{Value, json};
+
+    use super::WebVpnCatalog;
+
+    fn service(id: &str, name: &str, server: &str, url_plus: Option<&str>) -> Value {
+        json!({
+            "id": id,
+            "name": name,
+            "icon": "",
+            "server": server,
+            "description": "",
+            "type": "http",
+            "urlPlus": url_plus,
+            "hostMd5": url_plus.is_none().then_some("abc"),
+            "gatewayVo": {
+                "id": "", "name": "", "uniqueNo": "", "server": "", "description": "",
+                "type": "", "adminAddr": "", "nginxPort": "", "connectState": "",
+                "publicServer": ""
+            }
+        })
+    }
+
+    fn group(title: &str, services: Vec<Value>, children: Vec<Value>) -> Value {
+        json!({"title": title, "key": title, "children": children, "serviceList": services})
+    }
+
+    #[test]
+    fn browse() {
+        let jw = service("1", "教务系统", "http://10.0.0.1/web", None);
+        let lib = service(
+            "2",
+            "Library",
+            "lib.example.com",
+            Some("/http/webvpnlib/index"),
+        );
+        let mail = service("3", "Mail", "https://mail.example.com", None);
+        let tree = group(
+            "root",
+            vec![],
+            vec![
+                group("教学", vec![jw.clone()], vec![]),
+                group(
+                    "资源",
+                    vec![],
+                    vec![group("图书", vec![lib.clone(), jw.clone()], vec![])],
+                ),
+            ],
+        );
+        let catalog = WebVpnCatalog::new(
+            "https://vpn.example.com/",
+            &serde_json::from_value(tree).unwrap(),
+            &serde_json::from_value::<Vec<_>>(json!([jw, mail])).unwrap(),
+            &serde_json::from_value::<Vec<_>>(json!([mail, lib])).unwrap(),
+        );
+
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog.get("1").unwrap().group_path, ["教学"]);
+        assert_eq!(catalog.get("2").unwrap().group_path, ["资源", "图书"]);
+        assert!(catalog.get("3").unwrap().group_path.is_empty());
+        assert_eq!(catalog.group(&["资源"]).len(), 1);
+        assert_eq!(catalog.group(&[]).len(), 3);
+
+        assert_eq!(catalog.search("library")[0].service.id, "2");
+        let visited: Vec<&str> = catalog
+            .recently_visited()
+            .iter()
+            .map(|entry| entry.service.id.as_str())
+            .collect();
+        assert_eq!(visited, ["3", "2"]);
+
+        assert_eq!(
+            catalog.launch_url("1"),
+            Some("https://vpn.example.com/http/webvpnabc/web")
+        );
+        assert_eq!(
+            catalog.launch_url("2"),
+            Some("https://vpn.example.com/http/webvpnlib/index")
+        );
+        assert_eq!(
+            catalog.launch_url("3"),
+            Some("https://vpn.example.com/https/webvpnabc")
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use super::{sso_redirect::STATIC_SERVER_MAP, webvpn_type::ElinkServiceData};
+use crate::{base::properties::PersistentProperty, internals::fields::ROOT_VPN};
+
+/// Map a campus url to its WebVPN url and back.
+///
+/// The WebVPN serves `http://host:port/path` as `{vpn}/http/webvpn{host_md5}/path`,
+/// the `host_md5` is salted by the gateway, so it is learned from
+/// [`ElinkServiceData`] instead of computed.
+///
+/// ```
+/// # use cczuni::impls::services::webvpn_rewriter::WebVpnUrlRewriter;
+/// let mut rewriter = WebVpnUrlRewriter::new("https://vpn.example.com");
+/// rewriter.learn("http://10.0.0.1:8080", "abcdef");
+/// let proxied = rewriter.rewrite("http://10.0.0.1:8080/index?id=1").unwrap();
+/// assert_eq!(proxied, "https://vpn.example.com/http/webvpnabcdef/index?id=1");
+/// assert_eq!(rewriter.reverse(&proxied).unwrap(), "http://10.0.0.1:8080/index?id=1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebVpnUrlRewriter {
+    vpn: String,
+    /// The `host_md5` of every origin, e.g. `http://219.230.159.132`
+    hosts: HashMap<String, String>,
+}
+
+impl PersistentProperty for WebVpnUrlRewriter {
+    const KEY: &'static str = "webvpn-hosts";
+}
+
+impl WebVpnUrlRewriter {
+    pub fn new(vpn: impl Into<String>) -> Self {
+        Self {
+            vpn: vpn.into().trim_end_matches('/').to_string(),
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// Only knows the hosts of [`STATIC_SERVER_MAP`], used until the services are fetched.
+    pub fn fallback(vpn: impl Into<String>) -> Self {
+        let mut rewriter = Self::new(vpn);
+        for (origin, proxied) in STATIC_SERVER_MAP.iter() {
+            if let Some(host_md5) = proxied
+                .strip_prefix(ROOT_VPN)
+                .and_then(|path| path.rsplit_once("/webvpn"))
+                .map(|(_, host_md5)| host_md5)
+            {
+                rewriter.learn(origin, host_md5);
+            }
+        }
+        rewriter
+    }
+
+    pub fn vpn(&self) -> &str {
+        &self.vpn
+    }
+
+    /// Return `false` if `server` isn't an url.
+    pub fn learn(&mut self, server: &str, host_md5: &str) -> bool {
+        match origin(server) {
+            Some(origin) if !host_md5.is_empty() => {
+                self.hosts.insert(origin, host_md5.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Learn the hosts of the services, from `host_md5` or else from the proxied `url_plus`.
+    ///
+    /// Return the number of learned hosts.
+    pub fn learn_services(&mut self, services: &[ElinkServiceData]) -> usize {
+        services
+            .iter()
+            .filter(|service| {
+                let server = match service.server.contains("://") {
+                    true => service.server.clone(),
+                    false => format!("{}://{}", scheme_of(&service.type_of), service.server),
+                };
+                let host_md5 = service.host_md5.clone().or_else(|| {
+                    service
+                        .url_plus
+                        .as_deref()
+                        .and_then(|url| url.split_once("/webvpn"))
+                        .map(|(_, rest)| {
+                            rest.split(['/', '?', '#'])
+                                .next()
+                                .unwrap_or_default()
+                                .to_string()
+                        })
+                });
+                host_md5.is_some_and(|host_md5| self.learn(&server, &host_md5))
+            })
+            .count()
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        origin(url).is_some_and(|origin| self.hosts.contains_key(&origin))
+    }
+
+    /// The WebVPN url of `url`, `None` if its host wasn't learned.
+    ///
+    /// A missing path stays missing, so the result can be used as a root.
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let parsed = Url::parse(url).ok()?;
+        let host_md5 = self.hosts.get(&origin(url)?)?;
+        Some(format!(
+            "{}/{}/webvpn{}{}",
+            self.vpn,
+            parsed.scheme(),
+            host_md5,
+            after_authority(url)
+        ))
+    }
+
+    /// The campus url of a WebVPN `url`, `None` if it isn't proxied by a learned host.
+    pub fn reverse(&self, url: &str) -> Option<String> {
+        let path = url.strip_prefix(&self.vpn)?;
+        let (scheme, rest) = path.strip_prefix('/')?.split_once("/webvpn")?;
+        self.hosts
+            .iter()
+            .filter(|(origin, _)| origin.starts_with(&format!("{}://", scheme)))
+            .find_map(|(origin, host_md5)| {
+                let rest = rest.strip_prefix(host_md5.as_str())?;
+                match rest.is_empty() || rest.starts_with(['/', '?', '#']) {
+                    true => Some(format!("{}{}", origin, rest)),
+                    false => None,
+                }
+            })
+    }
+
+    /// Replace the proxied links of a page by the campus urls,
+    /// both `{vpn}/http/webvpn{host_md5}/a` and `/http/webvpn{host_md5}/a`.
+    ///
+    /// Only the quoted attribute values of the tags are replaced,
+    /// the text and scripts of the page are kept as is.
+    pub fn reverse_links(&self, html: &str) -> String {
+        // replaced between ASCII quotes, the output is UTF-8 as well
+        String::from_utf8(self.reverse_links_bytes(html.as_bytes()))
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+    }
+
+    /// [`Self::reverse_links`] of a page in any ASCII compatible charset, e.g. the GBK of the jwcas.
+    ///
+    /// The links are ASCII, so the page is scanned without decoding it.
+    pub fn reverse_links_bytes(&self, html: &[u8]) -> Vec<u8> {
+        let bytes = html;
+        let mut output = Vec::with_capacity(html.len());
+        let (mut copied, mut index) = (0, 0);
+        let (mut in_tag, mut after_equals) = (false, false);
+        while index < bytes.len() {
+            match bytes[index] {
+                b'<' if !in_tag => in_tag = true,
+                b'>' if in_tag => in_tag = false,
+                b'=' if in_tag => {
+                    after_equals = true;
+                    index += 1;
+                    continue;
+                }
+                quote @ (b'"' | b'\'') if in_tag => {
+                    let start = index + 1;
+                    let end = bytes[start..]
+                        .iter()
+                        .position(|&byte| byte == quote)
+                        .map_or(bytes.len(), |length| start + length);
+                    if after_equals
+                        && let Ok(value) = std::str::from_utf8(&bytes[start..end])
+                        && let Some(link) = self.reverse_link(value)
+                    {
+                        output.extend_from_slice(&bytes[copied..start]);
+                        output.extend_from_slice(link.as_bytes());
+                        copied = end;
+                    }
+                    // continue after the closing quote
+                    index = end;
+                }
+                byte if byte.is_ascii_whitespace() => {
+                    index += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            after_equals = false;
+            index += 1;
+        }
+        output.extend_from_slice(&bytes[copied..]);
+        output
+    }
+
+    /// [`Self::reverse`] of an attribute value, which may be relative to the vpn root.
+    fn reverse_link(&self, value: &str) -> Option<String> {
+        match value.starts_with('/') && !value.starts_with("//") {
+            true => self.reverse(&format!("{}{}", self.vpn, value)),
+            false => self.reverse(value),
+        }
+    }
+}
+
+/// `scheme://host[:port]`, without the default port.
+fn origin(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    url.host_str()?;
+    Some(url.origin().ascii_serialization())
+}
+
+/// The path, query and fragment of `url` as written.
+fn after_authority(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.find(['/', '?', '#'])
+        .map_or("", |index| &rest[index..])
+}
+
+fn scheme_of(type_of: &str) -> &'static str {
+    match type_of.eq_ignore_ascii_case("https") {
+        true => "https",
+        false => "http",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WebVpnUrlRewriter;
+    use crate::internals::fields::ROOT_VPN;
+
+    #[test]
+    fn fallback_map() {
+        let rewriter = WebVpnUrlRewriter::fallback(ROOT_VPN);
+        let proxied = "https://zmvpn.cczu.edu.cn/http/webvpndc2d086cb5b297c15e661687e73c1549";
+        assert_eq!(
+            rewriter.rewrite("http://219.230.159.132").as_deref(),
+            Some(proxied)
+        );
+        assert_eq!(
+            rewriter
+                .reverse(&format!("{}/web_cas/a.aspx", proxied))
+                .as_deref(),
+            Some("http://219.230.159.132/web_cas/a.aspx")
+        );
+        assert!(rewriter.rewrite("http://219.230.159.133").is_none());
+    }
+
+    #[test]
+    fn ports_and_schemes() {
+        let mut rewriter = WebVpnUrlRewriter::new("https://vpn.example.com/");
+        assert!(rewriter.learn("https://lib.example.com:443/", "aaa"));
+        assert!(rewriter.learn("http://lib.example.com:8080", "bbb"));
+        assert!(!rewriter.learn("not an url", "ccc"));
+
+        assert_eq!(
+            rewriter.rewrite("https://lib.example.com/?q=1").as_deref(),
+            Some("https://vpn.example.com/https/webvpnaaa/?q=1")
+        );
+        assert_eq!(
+            rewriter.rewrite("http://lib.example.com:8080/a").as_deref(),
+            Some("https://vpn.example.com/http/webvpnbbb/a")
+        );
+        assert!(rewriter.rewrite("http://lib.example.com/a").is_none());
+
+        assert_eq!(
+            rewriter
+                .reverse("https://vpn.example.com/http/webvpnbbb/a")
+                .as_deref(),
+            Some("http://lib.example.com:8080/a")
+        );
+        assert!(
+            rewriter
+                .reverse("https://vpn.example.com/http/webvpnbbbc/a")
+                .is_none()
+        );
+        assert!(
+            rewriter
+                .reverse("https://vpn.example.com/https/webvpnbbb/a")
+                .is_none()
+        );
+        assert_eq!(
+            rewriter.reverse_links(
+                r#"<a href="/http/webvpnbbb/a">a</a><img src="https://vpn.example.com/https/webvpnaaa/b.png">"#
+            ),
+            r#"<a href="http://lib.example.com:8080/a">a</a><img src="https://lib.example.com/b.png">"#
+        );
+        // text, scripts and other hashes are kept
+        assert_eq!(
+            rewriter.reverse_links(
+                r#"<p title="a > b" data-x='/http/webvpnbbb'>/http/webvpnbbb/a</p><script>let a = "/http/webvpnbbb/a";</script><a href="/http/webvpnbbbc/a">"#
+            ),
+            r#"<p title="a > b" data-x='http://lib.example.com:8080'>/http/webvpnbbb/a</p><script>let a = "/http/webvpnbbb/a";</script><a href="/http/webvpnbbbc/a">"#
+        );
+        // 教务 in GBK
+        assert_eq!(
+            rewriter.reverse_links_bytes(b"<a href=\"/http/webvpnbbb/a\">\xbd\xcc\xce\xf1</a>"),
+            b"<a href=\"http://lib.example.com:8080/a\">\xbd\xcc\xce\xf1</a>"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Write},
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+};
+
+use super::webvpn_type::ElinkProxyData;
+use crate::error::Error;
+
+/// An IPv4 network, always stored with the host bits cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cidr {
+    pub network: Ipv4Addr,
+    pub prefix: u8,
+}
+
+impl Cidr {
+    /// `None` if `prefix` is greater than 32.
+    pub fn new(address: Ipv4Addr, prefix: u8) -> Option<Self> {
+        (prefix <= 32).then(|| Self {
+            network: Ipv4Addr::from(u32::from(address) & mask_bits(prefix)),
+            prefix,
+        })
+    }
+
+    pub fn mask(&self) -> Ipv4Addr {
+        Ipv4Addr::from(mask_bits(self.prefix))
+    }
+
+    pub fn contains(&self, address: Ipv4Addr) -> bool {
+        u32::from(address) & mask_bits(self.prefix) == u32::from(self.network)
+    }
+
+    /// The smallest set of networks covering `start..=end`.
+    pub fn range(start: Ipv4Addr, end: Ipv4Addr) -> Vec<Self> {
+        let (mut start, end) = (u32::from(start) as u64, u32::from(end) as u64);
+        let mut cidrs = vec![];
+        while start <= end {
+            // the largest block aligned on `start` which doesn't pass `end`
+            let mut size = 1u64 << start.trailing_zeros().min(32);
+            while start + size - 1 > end {
+                size >>= 1;
+            }
+            let prefix = 32 - size.trailing_zeros() as u8;
+            cidrs.push(Self {
+                network: Ipv4Addr::from(start as u32),
+                prefix,
+            });
+            start += size;
+        }
+        cidrs
+    }
+}
+
+fn mask_bits(prefix: u8) -> u32 {
+    match prefix {
+        0 => 0,
+        _ => u32::MAX << (32 - prefix.min(32)),
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = Error;
+
+    /// Parse `a.b.c.d`, `a.b.c.d/24` or `a.b.c.d/255.255.255.0`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let address = address
+            .trim()
+            .parse::<Ipv4Addr>()
+            .map_err(|e| Error::parse_with("cidr", format!("Invalid address in {}", s), e))?;
+        let prefix = match prefix.map(str::trim) {
+            None => 32,
+            Some(prefix) => match prefix.parse::<u8>() {
+                Ok(prefix) => prefix,
+                Err(_) => {
+                    let mask = u32::from(prefix.parse::<Ipv4Addr>().map_err(|e| {
+                        Error::parse_with("cidr", format!("Invalid prefix in {}", s), e)
+                    })?);
+                    if mask.leading_ones() + mask.trailing_zeros() != 32 {
+                        return Err(Error::parse("cidr", format!("Invalid netmask in {}", s)));
+                    }
+                    mask.leading_ones() as u8
+                }
+            },
+        };
+        Self::new(address, prefix)
+            .ok_or_else(|| Error::parse("cidr", format!("Prefix longer than 32 in {}", s)))
+    }
+}
+
+/// A host name rule of the gateway white list.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DomainRule {
+    /// `cczu.edu.cn`
+    Exact(String),
+    /// `*.cczu.edu.cn`, the domain itself and every subdomain.
+    Suffix(String),
+    /// Any other pattern with `*`, e.g. `lib*.cczu.edu.cn`.
+    Wildcard(String),
+}
+
+impl DomainRule {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_end_matches('.').to_ascii_lowercase();
+        let valid = !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '*' | '_'));
+        if !valid {
+            return None;
+        }
+        Some(match s.strip_prefix("*.").or_else(|| s.strip_prefix('.')) {
+            Some(suffix) if !suffix.contains('*') && !suffix.is_empty() => {
+                Self::Suffix(suffix.to_string())
+            }
+            _ if s.contains('*') => Self::Wildcard(s),
+            _ => Self::Exact(s),
+        })
+    }
+}
+
+/// The split routing advertised by the WebVPN gateways,
+/// see [`super::webvpn::WebVPNService::webvpn_get_routes`].
+///
+/// The `whiteList`, `inIpList` and `inIpListByGatewayMap` entries of every gateway are merged:
+/// a network covered by another one is dropped, an entry which can't be parsed
+/// is kept in [`Self::skipped`].
+///
+/// ```
+/// # use cczuni::impls::services::webvpn_routes::WebVpnRoutes;
+/// let routes = WebVpnRoutes::from_entries(
+///     ["*.cczu.edu.cn", "219.230.159.0/24", "219.230.159.7", "10.0.0.0-10.0.1.255"],
+///     [],
+/// );
+/// assert_eq!(routes.to_cidr_list(), "10.0.0.0/23\n219.230.159.0/24\n");
+/// assert!(routes.to_pac("SOCKS5 127.0.0.1:1080").contains("dnsDomainIs(host, \".cczu.edu.cn\")"));
+/// assert!(routes.to_clash_rule_set().contains("  - DOMAIN-SUFFIX,cczu.edu.cn\n"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebVpnRoutes {
+    pub domains: Vec<DomainRule>,
+    pub cidrs: Vec<Cidr>,
+    /// The DNS servers of the gateways, to resolve the campus hosts.
+    pub dns: Vec<IpAddr>,
+    pub skipped: Vec<String>,
+}
+
+impl WebVpnRoutes {
+    pub fn new(data: &ElinkProxyData) -> Self {
+        let gateways = &data.gateway_list;
+        let entries = gateways.iter().flat_map(|gateway| {
+            gateway
+                .white_list
+                .iter()
+                .chain(gateway.in_ip_list.iter())
+                .chain(gateway.in_ip_list_by_gateway_map.values().flatten())
+                .map(String::as_str)
+        });
+        let dns = gateways
+            .iter()
+            .flat_map(|gateway| gateway.dns.split([',', ';', ' ']))
+            .filter_map(|dns| dns.trim().parse().ok());
+        Self::from_entries(entries, dns)
+    }
+
+    /// Build from raw gateway entries, an entry is a host name pattern, an address,
+    /// a network or an address range `a.b.c.d-e.f.g.h`.
+    pub fn from_entries<'a>(
+        entries: impl IntoIterator<Item = &'a str>,
+        dns: impl IntoIterator<Item = IpAddr>,
+    ) -> Self {
+        let mut domains = BTreeSet::new();
+        let mut cidrs = BTreeSet::new();
+        let mut skipped = vec![];
+        for entry in entries.into_iter().map(str::trim).filter(|e| !e.is_empty()) {
+            if let Ok(cidr) = entry.parse::<Cidr>() {
+                cidrs.insert(cidr);
+            } else if let Some((start, end)) = entry.split_once('-')
+                && let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse())
+            {
+                cidrs.extend(Cidr::range(start, end));
+            } else if let Some(domain) = DomainRule::parse(entry) {
+                domains.insert(domain);
+            } else if !skipped.iter().any(|s| s == entry) {
+                skipped.push(entry.to_string());
+            }
+        }
+
+        // sorted by network then prefix, so a covering network comes first
+        let mut merged: Vec<Cidr> = vec![];
+        for cidr in cidrs {
+            if !merged
+                .iter()
+                .any(|kept| kept.prefix <= cidr.prefix && kept.contains(cidr.network))
+            {
+                merged.push(cidr);
+            }
+        }
+        // in the order of the gateways, the first one is preferred
+        let mut seen = BTreeSet::new();
+        let dns = dns.into_iter().filter(|dns| seen.insert(*dns)).collect();
+        Self {
+            domains: domains.into_iter().collect(),
+            cidrs: merged,
+            dns,
+            skipped,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty() && self.cidrs.is_empty()
+    }
+
+    /// One network per line, e.g. for `ip route` or a WireGuard `AllowedIPs`.
+    pub fn to_cidr_list(&self) -> String {
+        self.cidrs
+            .iter()
+            .map(|cidr| format!("{}\n", cidr))
+            .collect()
+    }
+
+    /// A PAC file sending the campus hosts to `proxy`, e.g. `SOCKS5 127.0.0.1:1080`,
+    /// and everything else `DIRECT`.
+    pub fn to_pac(&self, proxy: &str) -> String {
+        let proxy = proxy.replace(['"', '\\'], "");
+        let mut pac = String::from("function FindProxyForURL(url, host) {\n");
+        let _ = writeln!(pac, "    var proxy = \"{}\";", proxy);
+        pac.push_str("    host = host.toLowerCase();\n");
+        for domain in &self.domains {
+            let condition = match domain {
+                DomainRule::Exact(host) => format!("host == \"{}\"", host),
+                DomainRule::Suffix(suffix) => {
+                    format!("host == \"{0}\" || dnsDomainIs(host, \".{0}\")", suffix)
+                }
+                DomainRule::Wildcard(pattern) => format!("shExpMatch(host, \"{}\")", pattern),
+            };
+            let _ = writeln!(pac, "    if ({}) return proxy;", condition);
+        }
+        if !self.cidrs.is_empty() {
+            pac.push_str("    var ip = dnsResolve(host);\n    if (!ip) return \"DIRECT\";\n");
+            for cidr in &self.cidrs {
+                let _ = writeln!(
+                    pac,
+                    "    if (isInNet(ip, \"{}\", \"{}\")) return proxy;",
+                    cidr.network,
+                    cidr.mask()
+                );
+            }
+        }
+        pac.push_str("    return \"DIRECT\";\n}\n");
+        pac
+    }
+
+    /// A classical Clash/mihomo rule provider, the policy is chosen by the `RULE-SET` rule.
+    pub fn to_clash_rule_set(&self) -> String {
+        let mut rules = String::from("payload:\n");
+        for domain in &self.domains {
+            let _ = match domain {
+                DomainRule::Exact(host) => writeln!(rules, "  - DOMAIN,{}", host),
+                DomainRule::Suffix(suffix) => writeln!(rules, "  - DOMAIN-SUFFIX,{}", suffix),
+                DomainRule::Wildcard(pattern) => {
+                    writeln!(rules, "  - DOMAIN-WILDCARD,{}", pattern)
+                }
+            };
+        }
+        for cidr in &self.cidrs {
+            let _ = writeln!(rules, "  - IP-CIDR,{},no-resolve", cidr);
+        }
+        rules
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{Cidr, DomainRule, WebVpnRoutes};
+    use crate::error::Error;
+
+    #[test]
+    fn parse_entries() {
+        assert_eq!(
+            "10.1.2.3/255.255.0.0".parse::<Cidr>().unwrap(),
+            Cidr::new(Ipv4Addr::new(10, 1, 0, 0), 16).unwrap()
+        );
+        assert!(matches!(
+            "10.1.2.3/255.0.255.0".parse::<Cidr>(),
+            Err(Error::ParseError { page: "cidr", .. })
+        ));
+        assert!("10.1.2.3/33".parse::<Cidr>().is_err());
+        assert_eq!(
+            Cidr::range(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 6))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]
+        );
+        assert_eq!(
+            Cidr::range(Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST),
+            [Cidr::new(Ipv4Addr::UNSPECIFIED, 0).unwrap()]
+        );
+
+        let routes = WebVpnRoutes::from_entries(
+            [
+                "*.cczu.edu.cn",
+                "CCZU.edu.cn",
+                "lib*.example.com",
+                "10.0.0.0/8",
+                "10.1.0.0/16",
+                "not a host",
+                "",
+            ],
+            ["10.0.0.1", "10.0.0.2", "10.0.0.1"].map(|dns| dns.parse().unwrap()),
+        );
+        assert_eq!(
+            routes.domains,
+            [
+                DomainRule::Exact("cczu.edu.cn".into()),
+                DomainRule::Suffix("cczu.edu.cn".into()),
+                DomainRule::Wildcard("lib*.example.com".into()),
+            ]
+        );
+        assert_eq!(routes.to_cidr_list(), "10.0.0.0/8\n");
+        assert_eq!(routes.skipped, ["not a host"]);
+        assert_eq!(
+            routes.dns,
+            ["10.0.0.1", "10.0.0.2"].map(|dns| dns.parse::<IpAddr>().unwrap())
+        );
+    }
+
+    #[test]
+    fn exports() {
+        let routes = WebVpnRoutes::from_entries(["*.cczu.edu.cn", "219.230.159.0/24"], []);
+        assert_eq!(
+            routes.to_pac("PROXY 127.0.0.1:7890"),
+            r#"function FindProxyForURL(url, host) {
+    var proxy = "PROXY 127.0.0.1:7890";
+    host = host.toLowerCase();
+    if (host == "cczu.edu.cn" || dnsDomainIs(host, ".cczu.edu.cn")) return proxy;
+    var ip = dnsResolve(host);
+    if (!ip) return "DIRECT";
+    if (isInNet(ip, "219.230.159.0", "255.255.255.0")) return proxy;
+    return "DIRECT";
+}
+"#
+        );
+        assert_eq!(
+            routes.to_clash_rule_set(),
+            "payload:\n  - DOMAIN-SUFFIX,cczu.edu.cn\n  - IP-CIDR,219.230.159.0/24,no-resolve\n"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use reqwest::{
+    Method, Request, Response, ResponseBuilderExt, StatusCode, Url,
+    cookie::CookieStore,
+    header::{COOKIE, HeaderName, HeaderValue, LOCATION, SET_COOKIE},
+};
+use reqwest_cookie_store::CookieStoreMutex;
+
+use crate::{
+    base::transport::{Transport, TransportFuture},
+    error::{Error, Result},
+};
+
+/// A response served by [`MemoryTransport`].
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    /// `200` with the body.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::new(StatusCode::OK).body(body)
+    }
+
+    /// `200` with the json body.
+    pub fn json(body: &serde_json::Value) -> Self {
+        Self::ok(body.to_string()).header(
+            HeaderName::from_static("content-type"),
+            HeaderValue::from_static("application/json"),
+        )
+    }
+
+    /// `302` to the location, the login flows follow redirects themselves.
+    pub fn redirect(location: &str) -> Self {
+        let mut response = Self::new(StatusCode::FOUND);
+        if let Ok(location) = HeaderValue::from_str(location) {
+            response.headers.push((LOCATION, location));
+        }
+        response
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn cookie(self, cookie: &str) -> Self {
+        match HeaderValue::from_str(cookie) {
+            Ok(value) => self.header(SET_COOKIE, value),
+            Err(_) => self,
+        }
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    fn into_response(self, url: Url) -> Result<Response> {
+        let mut builder = http::Response::builder().status(self.status).url(url);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
+            .body(self.body)
+            .map_err(|e| Error::InvalidInput(format!("Invalid mock response: {}", e)))?;
+        Ok(Response::from(response))
+    }
+}
+
+/// A request received by [`MemoryTransport`].
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: reqwest::header::HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RecordedRequest {
+    pub fn body_text(&self) -> Option<&str> {
+        std::str::from_utf8(self.body.as_deref()?).ok()
+    }
+}
+
+#[derive(Default)]
+struct Routes {
+    responses: HashMap<(Method, String), VecDeque<MockResponse>>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Serve canned responses keyed by method and url, without any network.
+///
+/// A url with a query is matched exactly first, then without its query.
+/// The responses of one route are served in order and the last one is repeated,
+/// an unknown route is answered with `404`.
+///
+/// ```
+/// # use reqwest::Method;
+/// # use cczuni::impls::{client::DefaultClient, transport::{MemoryTransport, MockResponse}};
+/// let transport = MemoryTransport::new()
+///     .route(Method::GET, "http://example.com/", MockResponse::ok("hello"));
+/// let client = DefaultClient::builder().transport(transport.clone()).build().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryTransport {
+    routes: Arc<Mutex<Routes>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(self, method: Method, url: impl AsRef<str>, response: MockResponse) -> Self {
+        self.add_route(method, url, response);
+        self
+    }
+
+    pub fn add_route(&self, method: Method, url: impl AsRef<str>, response: MockResponse) {
+        let url = normalize(url.as_ref());
+        self.routes
+            .lock()
+            .unwrap()
+            .responses
+            .entry((method, url))
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Every request received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.routes.lock().unwrap().requests.clone()
+    }
+
+    fn respond(&self, request: &Request) -> MockResponse {
+        let mut routes = self.routes.lock().unwrap();
+        routes.requests.push(RecordedRequest {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: request.headers().clone(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(<[u8]>::to_vec),
+        });
+
+        let mut without_query = request.url().clone();
+        without_query.set_query(None);
+        let keys = [
+            (request.method().clone(), normalize(request.url().as_str())),
+            (request.method().clone(), normalize(without_query.as_str())),
+        ];
+        for key in keys {
+            if let Some(responses) = routes.responses.get_mut(&key) {
+                return match responses.len() {
+                    1 => responses[0].clone(),
+                    _ => responses.pop_front().unwrap(),
+                };
+            }
+        }
+        MockResponse::new(StatusCode::NOT_FOUND)
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn execute(&self, request: Request) -> TransportFuture {
+        let response = self.respond(&request);
+        let url = request.url().clone();
+        Box::pin(async move { response.into_response(url) })
+    }
+}
+
+fn normalize(url: &str) -> String {
+    Url::parse(url)
+        .map(String::from)
+        .unwrap_or_else(|_| url.to_string())
+}
+
+/// Keep the cookie store of a client working over a custom [`Transport`],
+/// `reqwest::Client` does this itself.
+pub(crate) struct CookieTransport {
+    pub(crate) inner: Arc<dyn Transport>,
+    pub(crate) cookies: Arc<CookieStoreMutex>,
+}
+
+impl Transport for CookieTransport {
+    fn execute(&self, mut request: Request) -> TransportFuture {
+        if !request.headers().contains_key(COOKIE)
+            && let Some(cookie) = self.cookies.cookies(request.url())
+        {
+            request.headers_mut().insert(COOKIE, cookie);
+        }
+        let inner = self.inner.clone();
+        let cookies = self.cookies.clone();
+        Box::pin(async move {
+            let response = inner.execute(request).await?;
+            cookies.set_cookies(
+                &mut response.headers().get_all(SET_COOKIE).iter(),
+                response.url(),
+            );
+            Ok(response)
+        })
+    }
+}
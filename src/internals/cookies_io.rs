@@ -12,6 +12,7 @@ pub trait CookiesIOExt {
     ) -> &mut Self;
 
     fn headers(&mut self, url: &Url) -> String;
+    fn remove_cookies(&mut self, url: &Url) -> &mut Self;
 }
 
 impl CookiesIOExt for CookieStore {
@@ -56,4 +57,23 @@ impl CookiesIOExt for CookieStore {
             .collect::<Vec<String>>()
             .join("; ")
     }
+
+    /// Remove every cookie sent to the host of `url`, whatever its path.
+    fn remove_cookies(&mut self, url: &Url) -> &mut Self {
+        let matched: Vec<(String, String, String)> = self
+            .iter_any()
+            .filter(|cookie| cookie.domain.matches(url))
+            .filter_map(|cookie| {
+                Some((
+                    cookie.domain.as_cow()?.into_owned(),
+                    String::from(&cookie.path),
+                    cookie.name().to_string(),
+                ))
+            })
+            .collect();
+        for (domain, path, name) in matched {
+            self.remove(&domain, &path, &name);
+        }
+        self
+    }
 }
@@ -1,9 +1,6 @@
 use std::sync::LazyLock;
 
-use reqwest::{
-    Url,
-    header::{HeaderMap, HeaderValue, USER_AGENT},
-};
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
 
 pub static DEFAULT_HEADERS: LazyLock<HeaderMap> = LazyLock::new(|| {
     let mut headers = HeaderMap::new();
@@ -15,12 +12,13 @@ pub static DEFAULT_HEADERS: LazyLock<HeaderMap> = LazyLock::new(|| {
     );
     headers
 });
-pub const ROOT_SSO: &'static str = "http://sso.cczu.edu.cn";
-pub const ROOT_SSO_URL: LazyLock<Url> = LazyLock::new(|| Url::parse(ROOT_SSO).unwrap());
-pub const ROOT_SSO_LOGIN: &'static str = "http://sso.cczu.edu.cn/sso/login";
-pub const ROOT_VPN: &'static str = "https://zmvpn.cczu.edu.cn";
-pub const ROOT_VPN_URL: LazyLock<Url> = LazyLock::new(|| Url::parse(ROOT_VPN).unwrap());
-pub const ROOT_YWTB: &'static str = "http://ywtb.cczu.edu.cn";
-// http://jwqywx.cczu.edu.cn/
-// http://202.195.102.7:8180
-pub const WECHAT_APP_API: &'static str = "http://jwqywx.cczu.edu.cn:8180";
+pub const ROOT_SSO: &str = "http://sso.cczu.edu.cn";
+pub const ROOT_SSO_LOGIN: &str = "http://sso.cczu.edu.cn/sso/login";
+pub const ROOT_VPN: &str = "https://zmvpn.cczu.edu.cn";
+pub const ROOT_YWTB: &str = "http://ywtb.cczu.edu.cn";
+pub const ROOT_JWQYWX: &str = "http://jwqywx.cczu.edu.cn";
+pub const WECHAT_APP_API: &str = "http://jwqywx.cczu.edu.cn:8180";
+pub const WECHAT_APP_API_FALLBACK: &str = "http://202.195.102.7:8180";
+pub const ROOT_JWCAS: &str = "http://219.230.159.132";
+pub const ROOT_ICCARD: &str = "http://wxxy.cczu.edu.cn";
+pub const ROOT_LAB: &str = "https://sysaqgl.cczu.edu.cn";
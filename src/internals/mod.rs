@@ -1,3 +1,6 @@
 pub mod cookies_io;
 pub mod fields;
-pub mod recursion;
+pub mod redirect;
+pub mod request;
+pub mod session;
+pub mod single_flight;
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use reqwest::{
+    Method, Request, RequestBuilder, Response, StatusCode, Url,
+    header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, LOCATION},
+};
+
+use crate::{
+    base::client::Client,
+    error::{Error, Result},
+    internals::request::RequestBuilderExt,
+};
+
+/// Default of [`Client::max_redirects`].
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// One request of a redirect chain and the status it was answered with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    pub method: Method,
+    pub url: Url,
+    pub status: StatusCode,
+}
+
+/// The final response and every request sent to reach it, the last hop included.
+#[derive(Debug)]
+pub struct Redirected {
+    pub response: Response,
+    pub chain: Vec<RedirectHop>,
+}
+
+/// The urls carried by [`Error::TooManyRedirects`] and [`Error::RedirectLoop`].
+fn urls(chain: &[RedirectHop]) -> Vec<Url> {
+    chain.iter().map(|hop| hop.url.clone()).collect()
+}
+
+/// `GET` `url` and follow its redirects, see [`follow_request`].
+pub async fn follow_get(client: &impl Client, url: &str) -> Result<Redirected> {
+    let request = client
+        .reqwest_client()
+        .get(url)
+        .headers(client.headers())
+        .build()?;
+    follow_request(client, request).await
+}
+
+/// Send `request` and follow the redirects of every response.
+///
+/// A relative `Location` is resolved against the current url.
+/// `301`, `302` and `303` continue with a `GET` without body (a `HEAD` stays a `HEAD`),
+/// `307` and `308` send the same method and body again.
+///
+/// Fail with [`Error::TooManyRedirects`] after [`Client::max_redirects`] redirects,
+/// or with [`Error::RedirectLoop`] when a url redirects to the same location twice.
+pub async fn follow_request(client: &impl Client, request: Request) -> Result<Redirected> {
+    let mut chain = vec![];
+    let mut edges = HashSet::new();
+    let mut request = request;
+    loop {
+        let method = request.method().clone();
+        let url = request.url().clone();
+        // the body is kept for a 307/308, a streaming one can't be sent again
+        let replay = request.try_clone();
+        let response = RequestBuilder::from_parts(client.reqwest_client(), request)
+            .send_with(client)
+            .await?;
+        let status = response.status();
+        chain.push(RedirectHop {
+            method: method.clone(),
+            url: url.clone(),
+            status,
+        });
+
+        let Some(location) = location(&response, &url)? else {
+            return Ok(Redirected { response, chain });
+        };
+        if !edges.insert((method.clone(), url.clone(), location.clone())) {
+            return Err(Error::RedirectLoop(urls(&chain)));
+        }
+        if chain.len() > client.max_redirects() {
+            return Err(Error::TooManyRedirects(urls(&chain)));
+        }
+
+        request = match status {
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+                let mut next = replay.ok_or_else(|| {
+                    Error::InvalidInput(format!("Can't send the body to {} again", location))
+                })?;
+                *next.url_mut() = location;
+                next
+            }
+            _ => {
+                let method = match method {
+                    Method::HEAD => Method::HEAD,
+                    _ => Method::GET,
+                };
+                let mut next = Request::new(method, location);
+                *next.headers_mut() = replay
+                    .map(|replay| replay.headers().clone())
+                    .unwrap_or_else(|| client.headers());
+                next.headers_mut().remove(CONTENT_TYPE);
+                next.headers_mut().remove(CONTENT_LENGTH);
+                next
+            }
+        };
+        if request.url().host_str() != url.host_str() {
+            request.headers_mut().remove(AUTHORIZATION);
+        }
+    }
+}
+
+/// Follow the redirect `response` to a `method` request sent elsewhere,
+/// e.g. the answer to a login form.
+///
+/// The body of that request is gone, so a `307`/`308` can't be followed.
+pub async fn follow_response(
+    client: &impl Client,
+    method: Method,
+    response: Response,
+) -> Result<Redirected> {
+    let url = response.url().clone();
+    let status = response.status();
+    let hop = RedirectHop {
+        method,
+        url: url.clone(),
+        status,
+    };
+    let Some(location) = location(&response, &url)? else {
+        return Ok(Redirected {
+            response,
+            chain: vec![hop],
+        });
+    };
+    if matches!(
+        status,
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT
+    ) {
+        return Err(Error::InvalidInput(format!(
+            "Can't send the body to {} again",
+            location
+        )));
+    }
+    let mut redirected = follow_get(client, location.as_str()).await?;
+    redirected.chain.insert(0, hop);
+    Ok(redirected)
+}
+
+/// The absolute target of a redirect response, `None` if it isn't a redirect.
+fn location(response: &Response, url: &Url) -> Result<Option<Url>> {
+    if !response.status().is_redirection() || response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let Some(location) = response.headers().get(LOCATION) else {
+        return Ok(None);
+    };
+    let location = location
+        .to_str()
+        .map_err(|e| Error::parse_with("redirect", "Invalid location header", e))?;
+    url.join(location)
+        .map(Some)
+        .map_err(|e| Error::parse_with("redirect", "Invalid location header", e))
+}
+
+#[cfg(test)]
+mod test {
+    use reqwest::{Method, StatusCode};
+
+    use super::{follow_get, follow_request};
+    use crate::{
+        base::client::Client,
+        error::Error,
+        impls::{
+            client::DefaultClient,
+            transport::{MemoryTransport, MockResponse},
+        },
+    };
+
+    fn client(transport: MemoryTransport) -> DefaultClient {
+        DefaultClient::builder()
+            .transport(transport)
+            .max_redirects(3)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn relative_locations() {
+        let transport = MemoryTransport::new()
+            .route(
+                Method::GET,
+                "http://a.test/start",
+                MockResponse::redirect("next?step=1"),
+            )
+            .route(
+                Method::GET,
+                "http://a.test/next?step=1",
+                MockResponse::new(StatusCode::MOVED_PERMANENTLY)
+                    .header(reqwest::header::LOCATION, "/end".parse().unwrap()),
+            )
+            .route(Method::GET, "http://a.test/end", MockResponse::ok("done"));
+        let redirected = follow_get(&client(transport), "http://a.test/start")
+            .await
+            .unwrap();
+        assert_eq!(redirected.response.text().await.unwrap(), "done");
+        let statuses: Vec<u16> = redirected
+            .chain
+            .iter()
+            .map(|hop| hop.status.as_u16())
+            .collect();
+        assert_eq!(statuses, [302, 301, 200]);
+    }
+
+    #[tokio::test]
+    async fn method_semantics() {
+        let transport = MemoryTransport::new()
+            .route(
+                Method::POST,
+                "http://a.test/form",
+                MockResponse::new(StatusCode::TEMPORARY_REDIRECT)
+                    .header(reqwest::header::LOCATION, "/moved".parse().unwrap()),
+            )
+            .route(
+                Method::POST,
+                "http://a.test/moved",
+                MockResponse::new(StatusCode::SEE_OTHER)
+                    .header(reqwest::header::LOCATION, "/result".parse().unwrap()),
+            )
+            .route(Method::GET, "http://a.test/result", MockResponse::ok("ok"));
+        let client = client(transport.clone());
+        let request = client
+            .reqwest_client()
+            .post("http://a.test/form")
+            .form(&[("name", "value")])
+            .build()
+            .unwrap();
+        let redirected = follow_request(&client, request).await.unwrap();
+        let methods: Vec<Method> = redirected
+            .chain
+            .iter()
+            .map(|hop| hop.method.clone())
+            .collect();
+        assert_eq!(methods, [Method::POST, Method::POST, Method::GET]);
+        let requests = transport.requests();
+        assert_eq!(requests[1].body_text(), Some("name=value"));
+        assert_eq!(requests[2].body_text(), None);
+    }
+
+    #[tokio::test]
+    async fn loops_and_limits() {
+        let transport = MemoryTransport::new()
+            .route(Method::GET, "http://a.test/a", MockResponse::redirect("/b"))
+            .route(Method::GET, "http://a.test/b", MockResponse::redirect("/a"));
+        let result = follow_get(&client(transport), "http://a.test/a").await;
+        assert!(matches!(result, Err(Error::RedirectLoop(urls)) if urls.len() == 3));
+
+        let transport = MemoryTransport::new()
+            .route(Method::GET, "http://a.test/1", MockResponse::redirect("/2"))
+            .route(Method::GET, "http://a.test/2", MockResponse::redirect("/3"))
+            .route(Method::GET, "http://a.test/3", MockResponse::redirect("/4"))
+            .route(Method::GET, "http://a.test/4", MockResponse::redirect("/5"));
+        let result = follow_get(&client(transport), "http://a.test/1").await;
+        assert!(matches!(result, Err(Error::TooManyRedirects(urls)) if urls.len() == 4));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{future::Future, sync::Arc};
+
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt};
+
+use crate::{
+    base::{client::Client, retry::RetryPolicy, transport::Transport},
+    error::{Error, Result},
+};
+
+/// Send requests through the [`RetryPolicy`] of a client.
+pub trait RequestBuilderExt {
+    /// Retry only if the method is idempotent.
+    fn send_with(self, client: &impl Client) -> impl Future<Output = Result<Response>>;
+    /// Retry even if the method is `POST`, use it for read-only queries only.
+    fn send_idempotent_with(self, client: &impl Client) -> impl Future<Output = Result<Response>>;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    fn send_with(self, client: &impl Client) -> impl Future<Output = Result<Response>> {
+        send(
+            self,
+            client.transport(),
+            client.retry_policy().clone(),
+            false,
+        )
+    }
+
+    fn send_idempotent_with(self, client: &impl Client) -> impl Future<Output = Result<Response>> {
+        send(
+            self,
+            client.transport(),
+            client.retry_policy().clone(),
+            true,
+        )
+    }
+}
+
+// owns the transport and policy so the future doesn't borrow the client across `.await`
+async fn send(
+    builder: RequestBuilder,
+    transport: Arc<dyn Transport>,
+    policy: RetryPolicy,
+    idempotent: bool,
+) -> Result<Response> {
+    let request = builder.build()?;
+    let idempotent = idempotent || RetryPolicy::is_idempotent(request.method());
+
+    let mut attempt = 1;
+    loop {
+        // streaming bodies can't be cloned and are sent once
+        let retry = match idempotent && attempt < policy.max_attempts {
+            true => request.try_clone(),
+            false => None,
+        };
+        let Some(next) = retry else {
+            return transport.execute(request).await;
+        };
+
+        match transport.execute(next).await {
+            Ok(response) if policy.retries_status(response.status()) => {}
+            Err(Error::Network(error)) if policy.retries_error(&error) => {}
+            result => return result,
+        }
+        tokio::time::sleep(policy.delay(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Read the whole body and give back an equivalent response,
+/// to inspect a page and still return it to the caller.
+pub async fn buffer_response(response: Response) -> Result<(Vec<u8>, Response)> {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version())
+        .url(response.url().clone());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let body = response.bytes().await?.to_vec();
+    let response = builder
+        .body(body.clone())
+        .map_err(|e| Error::InvalidInput(format!("Rebuild response failed: {}", e)))?;
+    Ok((body, Response::from(response)))
+}
@@ -0,0 +1,72 @@
+use reqwest::{Response, Url, header::LOCATION};
+use scraper::{Html, Selector};
+
+use crate::{
+    base::client::Client,
+    error::{Error, Result},
+    internals::{cookies_io::CookiesIOExt, request::RequestBuilderExt},
+};
+
+/// An expired SSO session is answered with a redirect to the SSO login page.
+pub fn redirects_to_sso(response: &Response) -> bool {
+    let location = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok());
+    (response.status().is_redirection() && location.is_some_and(|l| l.contains("/sso/login")))
+        || response.url().path().ends_with("/sso/login")
+}
+
+/// Some systems render the CAS login form in place of the page instead.
+pub fn is_sso_login_page(html: &str) -> bool {
+    let dom = Html::parse_document(html);
+    let execution = Selector::parse(r#"form input[name="execution"]"#).unwrap();
+    let password = Selector::parse(r#"form input[type="password"]"#).unwrap();
+    dom.select(&execution).next().is_some() && dom.select(&password).next().is_some()
+}
+
+/// Read the body of a page behind the SSO session,
+/// return [`Error::SessionExpired`] if the session is gone.
+pub async fn read_sso_page(response: Response) -> Result<String> {
+    if redirects_to_sso(&response) {
+        return Err(Error::SessionExpired("Redirected to SSO login".into()));
+    }
+    let html = response.text().await?;
+    if is_sso_login_page(&html) {
+        return Err(Error::SessionExpired("Got the SSO login page".into()));
+    }
+    Ok(html)
+}
+
+/// Ask the server to end the session at `logout`, then forget the cookies of `root`.
+///
+/// The cookies are removed even if the server can't be reached.
+pub async fn end_session(client: &impl Client, logout: String, root: &Url) -> Result<()> {
+    let result = client
+        .reqwest_client()
+        .get(logout)
+        .headers(client.headers())
+        .send_with(client)
+        .await;
+    client.cookies().lock().unwrap().remove_cookies(root);
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_sso_login_page;
+
+    #[test]
+    fn sso_login_page() {
+        let login = r#"<form id="fm1" method="post">
+            <input id="username" name="username" type="text"/>
+            <input id="password" name="password" type="password"/>
+            <input type="hidden" name="execution" value="e1s1"/>
+            <input type="hidden" name="_eventId" value="submit"/>
+        </form>"#;
+        assert!(is_sso_login_page(login));
+        assert!(!is_sso_login_page(
+            r#"<table id="GVkbk"><tr class="dg1-item"></tr></table>"#
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::watch;
+
+use crate::{base::client::Client, error::Result};
+
+enum Outcome<T> {
+    Pending,
+    Done(Result<T>),
+    /// The leader failed with an error which can't be shared, or was cancelled.
+    Retry,
+}
+
+/// Coalesce concurrent calls, the first caller runs the operation
+/// and the others await its result instead of running their own.
+///
+/// An error which can't be copied, e.g. [`crate::error::Error::Network`], isn't shared:
+/// a waiting caller runs the operation again, the same happens if the first caller is cancelled.
+pub struct SingleFlight<T> {
+    inflight: Mutex<Option<watch::Receiver<Outcome<T>>>>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            inflight: Mutex::new(None),
+        }
+    }
+}
+
+/// Forget the flight when the leader finishes or is dropped.
+struct Landing<'a, T>(&'a SingleFlight<T>);
+
+impl<T> Drop for Landing<'_, T> {
+    fn drop(&mut self) {
+        *self.0.inflight.lock().unwrap() = None;
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn run<F, Fut>(&self, operation: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        loop {
+            let joined = {
+                let mut inflight = self.inflight.lock().unwrap();
+                match &*inflight {
+                    Some(receiver) => Ok(receiver.clone()),
+                    None => {
+                        let (sender, receiver) = watch::channel(Outcome::Pending);
+                        *inflight = Some(receiver);
+                        Err(sender)
+                    }
+                }
+            };
+            let sender = match joined {
+                Ok(mut receiver) => {
+                    let Ok(outcome) = receiver
+                        .wait_for(|outcome| !matches!(outcome, Outcome::Pending))
+                        .await
+                    else {
+                        continue;
+                    };
+                    match &*outcome {
+                        Outcome::Done(Ok(value)) => return Ok(value.clone()),
+                        Outcome::Done(Err(error)) => match error.share() {
+                            Some(error) => return Err(error),
+                            None => continue,
+                        },
+                        _ => continue,
+                    }
+                }
+                Err(sender) => sender,
+            };
+
+            let landing = Landing(self);
+            let result = operation().await;
+            drop(landing);
+            sender.send_replace(match &result {
+                Ok(value) => Outcome::Done(Ok(value.clone())),
+                Err(error) => error
+                    .share()
+                    .map_or(Outcome::Retry, |e| Outcome::Done(Err(e))),
+            });
+            return result;
+        }
+    }
+}
+
+/// The [`SingleFlight`] of type `T` shared by a client and its clones,
+/// kept in [`crate::base::properties::Properties`].
+pub async fn client_flight<T>(client: &impl Client) -> Arc<SingleFlight<T>>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let properties = client.properties();
+    if let Some(flight) = properties.read().await.get::<Arc<SingleFlight<T>>>() {
+        return flight.clone();
+    }
+    let mut properties = properties.write().await;
+    match properties.get::<Arc<SingleFlight<T>>>() {
+        Some(flight) => flight.clone(),
+        None => {
+            let flight = Arc::new(SingleFlight::new());
+            properties.insert(flight.clone());
+            flight
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::SingleFlight;
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn coalesce_calls() {
+        let flight = Arc::new(SingleFlight::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let call = || {
+            let flight = flight.clone();
+            let runs = runs.clone();
+            async move {
+                flight
+                    .run(|| async {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok(runs.load(Ordering::SeqCst))
+                    })
+                    .await
+            }
+        };
+        let (a, b, c) = tokio::join!(call(), call(), call());
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (1, 1, 1));
+
+        // a finished flight isn't reused
+        assert_eq!(call().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn share_errors() {
+        let flight: Arc<SingleFlight<()>> = Arc::new(SingleFlight::new());
+        let call = || {
+            let flight = flight.clone();
+            async move {
+                flight
+                    .run(|| async {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Err(Error::AuthenticationFailed("wrong password".into()))
+                    })
+                    .await
+            }
+        };
+        let (a, b) = tokio::join!(call(), call());
+        assert!(matches!(a, Err(Error::AuthenticationFailed(_))));
+        assert!(matches!(b, Err(Error::AuthenticationFailed(_))));
+    }
+}
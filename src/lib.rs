@@ -1,4 +1,5 @@
 pub mod base;
+pub mod error;
 pub mod impls;
 #[cfg(feature = "internals")]
 pub mod internals;
@@ -6,8 +7,12 @@ pub mod internals;
 pub(crate) mod internals;
 
 pub mod extension;
+#[cfg(feature = "mock-server")]
+pub mod mock;
 pub mod utils;
 
+pub use error::{Error, Result};
+
 #[cfg(feature = "full")]
 #[cfg(test)]
 mod test {
@@ -24,7 +29,7 @@ mod test {
             login::sso::SSOUniversalLogin,
             services::webvpn::WebVPNService,
         },
-        internals::recursion::recursion_redirect_handle,
+        internals::redirect::follow_get,
     };
     #[tokio::test]
     async fn test_webvpn() {
@@ -41,10 +46,10 @@ mod test {
         }
 
         impl<C: Client + Clone> Application<C> for Foo<C> {
-            async fn from_client(client: &C) -> Self {
-                Self {
+            async fn from_client(client: &C) -> crate::Result<Self> {
+                Ok(Self {
                     client: client.clone(),
-                }
+                })
             }
         }
 
@@ -56,19 +61,17 @@ mod test {
                     .send()
                     .await
                     .unwrap();
-                recursion_redirect_handle(self.client.clone(), " url")
-                    .await
-                    .unwrap();
+                follow_get(&self.client, " url").await.unwrap();
             }
         }
 
         tokio::spawn(async {
             let client = DefaultClient::default();
             client.sso_universal_login().await.unwrap();
-            let foo = client.visit::<Foo<_>>().await;
-            let cas = client.visit::<JwcasApplication<_>>().await;
+            let foo = client.visit::<Foo<_>>().await.unwrap();
+            let cas = client.visit::<JwcasApplication<_>>().await.unwrap();
             foo.login().await;
-            let app = client.visit::<JwqywxApplication<_>>().await;
+            let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
             app.login().await.unwrap();
             app.get_grades().await.unwrap();
             cas.get_techplans().await.unwrap();
@@ -78,7 +81,7 @@ mod test {
     #[tokio::test]
     async fn calendar() {
         let client = DefaultClient::default();
-        let app = client.visit::<JwqywxApplication<_>>().await;
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
         app.login().await.unwrap();
         let matrix = app.get_classinfo_week_matrix().await.unwrap();
         parse_week_matrix(matrix).unwrap();
@@ -86,7 +89,7 @@ mod test {
     #[tokio::test]
     async fn test_jwqywx() {
         let client = DefaultClient::default();
-        let app = client.visit::<JwqywxApplication<_>>().await;
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
         app.login().await.unwrap();
         let matrix = app.get_classinfo_week_matrix().await.unwrap();
         for mut c in parse_week_matrix(matrix).unwrap() {
@@ -98,7 +101,7 @@ mod test {
     #[tokio::test]
     async fn test_grade() {
         let client = DefaultClient::default();
-        let app = client.visit::<JwqywxApplication<_>>().await;
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
         app.login().await.unwrap();
         let grades = app.get_grades().await.unwrap();
         println!("{:?}", grades);
@@ -108,7 +111,7 @@ mod test {
     async fn test_plan() {
         let client = DefaultClient::default();
         client.sso_universal_login().await.unwrap();
-        let app = client.visit::<JwcasApplication<_>>().await;
+        let app = client.visit::<JwcasApplication<_>>().await.unwrap();
         app.login().await.unwrap();
         let plans = app.get_techplans().await.unwrap();
         println!("{:?}", plans);
@@ -117,7 +120,7 @@ mod test {
     #[tokio::test]
     async fn test_rank() {
         let client = DefaultClient::default();
-        let app = client.visit::<JwqywxApplication<_>>().await;
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
         app.login().await.unwrap();
         println!("{:?}", app.get_credits_and_rank().await.unwrap());
     }
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json, Router,
+    extract::Query,
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+
+use super::{SharedState, html};
+
+pub(super) fn routes() -> Router<SharedState> {
+    Router::new()
+        .route(
+            "/wechat/callinterface/queryElecBuilding.html",
+            post(buildings),
+        )
+        .route("/wechat/callinterface/queryElecRoomInfo.html", post(room))
+        .route(
+            "/labexam/examIDSLogin.php",
+            get(|| async { html("<html><body>实验室安全考试</body></html>".into()) }),
+        )
+        .route(
+            "/labexam/exam_xuexi_online.php",
+            post(|| async { Json(json!({"status": 1, "shichang": "30"})) }),
+        )
+}
+
+/// The area, building and room are passed as json strings in the query.
+fn field(query: &HashMap<String, String>, name: &str) -> Value {
+    query
+        .get(name)
+        .and_then(|value| serde_json::from_str(value).ok())
+        .unwrap_or_else(|| json!({}))
+}
+
+async fn buildings(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+    Json(json!({
+        "area": field(&query, "area"),
+        "errmsg": "",
+        "buildingtab": [
+            {"building": "1号楼", "buildingid": "1"},
+            {"building": "2号楼", "buildingid": "2"},
+        ],
+        "aid": query.get("aid").cloned().unwrap_or_default(),
+        "account": query.get("account").cloned().unwrap_or_default(),
+        "retcode": "0",
+    }))
+}
+
+async fn room(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+    let room = field(&query, "room");
+    Json(json!({
+        "area": field(&query, "area"),
+        "errmsg": "",
+        "meterflag": "amt",
+        "bal": "",
+        "building": field(&query, "building"),
+        "room": {"roomid": room["roomid"], "room": room["roomid"]},
+        "pkgflag": "none",
+        "price": "0.5",
+        "pkgtab": [],
+        "floor": {"floorid": "", "floor": ""},
+        "aid": query.get("aid").cloned().unwrap_or_default(),
+        "account": query.get("account").cloned().unwrap_or_default(),
+        "retcode": "0",
+        "errmsg_extra": null,
+    }))
+}
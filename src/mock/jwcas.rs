@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use axum::{
+    Form, Router,
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+
+use super::{SharedState, cookie, html};
+
+const LOGIN: &str = "/web_cas/web_cas_login_jwgl.aspx";
+
+pub(super) fn routes() -> Router<SharedState> {
+    Router::new()
+        .route(LOGIN, get(login))
+        .route("/web_cjgl/cx_cj_jxjhcj_xh.aspx", get(grades))
+        .route("/web_jxjh/jxjh_cx.aspx", get(techplan_page).post(techplans))
+        .route("/web_jxrw/cx_kb_xsgrkb.aspx", get(classes))
+}
+
+/// Open an ASP.NET session if the SSO is logged in, otherwise go to the SSO.
+async fn login(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    if !state.sso_session(&headers) {
+        return sso_login(&state);
+    }
+    let session = state.id("session");
+    state.sessions.lock().unwrap().jwcas.insert(session.clone());
+    (
+        [(
+            header::SET_COOKIE,
+            format!("ASP.NET_SessionId={}; Path=/", session),
+        )],
+        html("<html><body>教务管理系统</body></html>".into()),
+    )
+        .into_response()
+}
+
+fn sso_login(state: &SharedState) -> Response {
+    state.redirect(&format!("/sso/login?service={}{}", state.root, LOGIN))
+}
+
+/// Render the page if the ASP.NET session is alive.
+fn page(state: &SharedState, headers: &HeaderMap, body: impl FnOnce() -> String) -> Response {
+    let alive = cookie(headers, "ASP.NET_SessionId")
+        .is_some_and(|session| state.sessions.lock().unwrap().jwcas.contains(&session));
+    match alive {
+        true => html(format!("<html><body>{}</body></html>", body())),
+        false => sso_login(state),
+    }
+}
+
+fn rows(id: &str, rows: &[Vec<&str>]) -> String {
+    let rows = rows
+        .iter()
+        .map(|cells| {
+            let cells = cells
+                .iter()
+                .map(|cell| format!("<td>{}</td>", cell))
+                .collect::<String>();
+            format!(r#"<tr class="dg1-item">{}</tr>"#, cells)
+        })
+        .collect::<String>();
+    format!(
+        r#"<table id="{}"><tr class="dg1-head"><td>#</td></tr>{}</table>"#,
+        id, rows
+    )
+}
+
+async fn grades(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    page(&state, &headers, || {
+        rows(
+            "GVkbk",
+            &[
+                vec![
+                    "1",
+                    "24-25-1",
+                    "230101",
+                    "01",
+                    "10000001",
+                    "高等数学",
+                    "必修",
+                    "64",
+                    "4.0",
+                    "92",
+                ],
+                vec![
+                    "2",
+                    "24-25-1",
+                    "230101",
+                    "01",
+                    "10000002",
+                    "大学英语1",
+                    "必修",
+                    "48",
+                    "3.0",
+                    "85",
+                ],
+            ],
+        )
+    })
+}
+
+async fn techplan_page(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    page(&state, &headers, || {
+        r#"<form method="post">
+    <input type="hidden" name="__VIEWSTATE" value="viewstate"/>
+    <input type="hidden" name="__VIEWSTATEGENERATOR" value="generator"/>
+    <input type="hidden" name="__VIEWSTATEENCRYPTED" value=""/>
+    <input id="Txtcxxq" name="Txtcxxq" value="24-25-2"/>
+    <select id="DDnj" name="DDnj"><option value="2023">2023</option></select>
+</form>"#
+            .into()
+    })
+}
+
+async fn techplans(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    if form.get("__VIEWSTATE").is_none_or(|v| v != "viewstate") {
+        return html("<html><body>Invalid ViewState</body></html>".into());
+    }
+    page(&state, &headers, || {
+        rows(
+            "GVjxjh",
+            &[vec![
+                "1",
+                "1",
+                "10000001",
+                "高等数学",
+                "必修",
+                "64",
+                "4.0",
+                "考试",
+                "0",
+                "0",
+                "0",
+                "计算机科学与技术",
+                "计算机学院",
+            ]],
+        )
+    })
+}
+
+/// Teachers on top, then one row per lesson with a cell per day.
+async fn classes(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    page(&state, &headers, || {
+        let teachers = rows(
+            "GVxkall",
+            &[
+                vec!["1", "高等数学", "", "", "", "李四"],
+                vec!["2", "大学英语2", "", "", "", "王五"],
+            ],
+        );
+        let lesson = vec![
+            "第一节",
+            "高等数学 W101 1-16,",
+            "",
+            "大学英语2 A级 W2204 双 3-18,",
+            "",
+            "",
+            "",
+            "",
+        ];
+        let courses = rows(
+            "GVxkkb",
+            &[
+                lesson.clone(),
+                lesson,
+                vec!["第三节", "", "", "", "", "", "", ""],
+            ],
+        );
+        format!("{}{}", teachers, courses)
+    })
+}
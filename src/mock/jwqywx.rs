@@ -0,0 +1,194 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+
+use super::SharedState;
+
+const TERM: &str = "24-25-2";
+
+pub(super) fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/api/login", post(login))
+        .route("/api/xqall", get(terms))
+        .route("/api/cj_xh", post(grades))
+        .route("/api/cj_xh_xfjd", post(points))
+        .route("/api/ks_xs_kslb", post(exams))
+        .route("/api/pj_xspj_kcxx", post(evaluatable_classes))
+        .route("/api/pj_insert_xspj", post(evaluate))
+        .route("/api/kb_xq_xh", post(courses))
+}
+
+fn message(message: Value) -> Json<Value> {
+    Json(json!({"status": 1, "message": message, "token": null}))
+}
+
+/// Answer `401` without a valid bearer token.
+fn authorized(state: &SharedState, headers: &HeaderMap, body: impl FnOnce() -> Value) -> Response {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token.is_some_and(|token| state.sessions.lock().unwrap().jwqywx.contains(token)) {
+        true => message(body()).into_response(),
+        false => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+async fn login(State(state): State<SharedState>, Json(body): Json<Value>) -> Json<Value> {
+    let user = body["userid"].as_str().unwrap_or_default();
+    let password = body["userpwd"].as_str().unwrap_or_default();
+    if !state.verify(user, password) {
+        return Json(json!({"status": 0, "message": [], "token": null}));
+    }
+
+    let token = state.id("token");
+    state.sessions.lock().unwrap().jwqywx.insert(token.clone());
+    Json(json!({
+        "status": 1,
+        "token": token,
+        "message": [{
+            "yhdm": user,
+            "yhmc": "张三",
+            "yhsf": "学生",
+            "xq": TERM,
+            "dqz": 1,
+            "zc": 1,
+            "gh": user,
+            "smscode": "",
+            "xb": "男",
+            "yhqx": "student",
+            "yhid": format!("id-{}", user),
+        }],
+    }))
+}
+
+async fn terms() -> Json<Value> {
+    message(json!([{"xq": TERM}, {"xq": "24-25-1"}]))
+}
+
+async fn grades(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    authorized(&state, &headers, || {
+        json!([{
+            "bh": "230101",
+            "bj": "计算机231",
+            "xh": state.account.user,
+            "xm": "张三",
+            "kcdm": "10000001",
+            "kcmc": "高等数学",
+            "xq": 1,
+            "kclb": "01",
+            "lbmc": "必修",
+            "xs": 64,
+            "xf": 4.0,
+            "jsmc": "李四",
+            "ksxzm": 1,
+            "ksxz": "正常考试",
+            "kscj": "92",
+            "idn": 1,
+            "cj": 92.0,
+            "xfjd": 4.2,
+        }])
+    })
+}
+
+async fn points(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    authorized(&state, &headers, || {
+        json!([{
+            "bh": "230101",
+            "bj": "计算机231",
+            "xh": state.account.user,
+            "xm": "张三",
+            "xb": "男",
+            "xjqk": "在读",
+            "csny": "2005-01",
+            "xsid": "1",
+            "pjxfjd": 3.8,
+        }])
+    })
+}
+
+async fn exams(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    authorized(&state, &headers, || {
+        json!([{
+            "lb": "学分制考试",
+            "xq": TERM,
+            "xsbh": "230101",
+            "xsbj": "计算机231",
+            "xh": state.account.user,
+            "xm": "张三",
+            "BH": "230101",
+            "kcdm": "10000001",
+            "kch": "01",
+            "kcmc": "高等数学",
+            "xklb": "必修",
+            "yx": 1,
+            "id": 1,
+            "ksz": 18,
+            "zc": 18,
+            "jc1": 1,
+            "jc2": 2,
+            "jse": "W101",
+            "sj": "08:00-10:00",
+            "jkjs1": null,
+            "jkjs2": null,
+            "bz": null,
+            "bmmc": "武进校区",
+            "kssj": "2025-06-20 08:00",
+            "bj": "计算机231",
+            "sjxx": null,
+            "jseid": 101,
+        }])
+    })
+}
+
+async fn evaluatable_classes(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    authorized(&state, &headers, || {
+        json!([{
+            "bh": "230101",
+            "kcdm": "10000001",
+            "kcmc": "高等数学",
+            "kch": "01",
+            "lbdh": "01",
+            "jsdm": "T001",
+            "jsmc": "李四",
+            "pjqk": null,
+            "pjid": 1,
+            "jsid": "T001",
+        }])
+    })
+}
+
+async fn evaluate(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    authorized(&state, &headers, || json!([]))
+}
+
+/// One row per lesson, `kc1`..`kc7` are the days.
+async fn courses(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    authorized(&state, &headers, || {
+        let row = |monday: &str, wednesday: &str| {
+            json!({
+                "kc1": monday,
+                "kc2": "",
+                "kc3": wednesday,
+                "kc4": "",
+                "kc5": "",
+                "kc6": "",
+                "kc7": "",
+                "kcmc1": "高等数学",
+                "skjs1": "李四",
+                "kcmc2": "大学英语2",
+                "skjs2": "王五",
+            })
+        };
+        json!([
+            row("高等数学 W101 1-16,", "大学英语2 A级 W2204 双 3-18,"),
+            row("高等数学 W101 1-16,", "大学英语2 A级 W2204 双 3-18,"),
+            row("", ""),
+        ])
+    })
+}
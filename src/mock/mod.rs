@@ -0,0 +1,575 @@
+//! A local server emulating the campus systems, for tests without the campus network.
+//!
+//! Every system is served from one root, use [`MockCampus::endpoints`]
+//! or [`MockCampus::client`] to point a client at it.
+//!
+//! ```no_run
+//! # use cczuni::{mock::MockCampus, impls::login::sso::SSOUniversalLogin};
+//! # async fn example() -> cczuni::Result<()> {
+//! let campus = MockCampus::start().await?;
+//! let client = campus.client()?;
+//! client.sso_universal_login().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod iccard;
+mod jwcas;
+mod jwqywx;
+mod sso;
+mod webvpn;
+
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use axum::{
+    Router,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::{
+    base::{client::Account, endpoints::Endpoints},
+    error::Result,
+    impls::{builder::ClientBuilder, client::DefaultClient},
+};
+
+/// The account accepted by [`MockCampus::start`].
+pub const MOCK_USER: &str = "2300000000";
+pub const MOCK_PASSWORD: &str = "password";
+
+/// Configure a [`MockCampus`].
+pub struct MockCampusBuilder {
+    account: Account,
+    webvpn: bool,
+    captcha: Option<String>,
+    verification: Option<String>,
+}
+
+impl Default for MockCampusBuilder {
+    fn default() -> Self {
+        Self {
+            account: Account::new(MOCK_USER, MOCK_PASSWORD),
+            webvpn: false,
+            captcha: None,
+            verification: None,
+        }
+    }
+}
+
+impl MockCampusBuilder {
+    /// The only account accepted by the login flows.
+    pub fn account(mut self, account: Account) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Emulate a client outside of the campus network,
+    /// the SSO login page redirects to the WebVPN.
+    ///
+    /// Default: `false`
+    pub fn webvpn(mut self, webvpn: bool) -> Self {
+        self.webvpn = webvpn;
+        self
+    }
+
+    /// Demand the captcha in the SSO login form,
+    /// its image is served as the plain text of the captcha.
+    pub fn captcha(mut self, captcha: impl Into<String>) -> Self {
+        self.captcha = Some(captcha.into());
+        self
+    }
+
+    /// Demand a code sent by SMS after the password of the SSO login form.
+    pub fn verification(mut self, code: impl Into<String>) -> Self {
+        self.verification = Some(code.into());
+        self
+    }
+
+    /// Listen on a random local port.
+    pub async fn start(self) -> Result<MockCampus> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(MockState {
+            root: format!("http://{}", addr),
+            account: self.account,
+            webvpn: self.webvpn,
+            captcha: self.captcha,
+            verification: self.verification,
+            code_sent: AtomicBool::new(false),
+            sessions: Mutex::new(Sessions::default()),
+            counter: AtomicU64::new(0),
+        });
+        let router = Router::new()
+            .merge(sso::routes())
+            .merge(webvpn::routes())
+            .merge(jwqywx::routes())
+            .merge(jwcas::routes())
+            .merge(iccard::routes())
+            .with_state(state.clone());
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        Ok(MockCampus { addr, state, task })
+    }
+}
+
+/// A running mock server, stopped on drop.
+pub struct MockCampus {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    task: JoinHandle<()>,
+}
+
+impl MockCampus {
+    pub fn builder() -> MockCampusBuilder {
+        MockCampusBuilder::default()
+    }
+
+    /// Start with the default account, see [`MOCK_USER`] and [`MOCK_PASSWORD`].
+    pub async fn start() -> Result<Self> {
+        Self::builder().start().await
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::single_host(self.url())
+    }
+
+    pub fn account(&self) -> Account {
+        self.state.account.clone()
+    }
+
+    /// A builder with the account and endpoints of this server.
+    pub fn client_builder(&self) -> ClientBuilder {
+        ClientBuilder::new()
+            .account(self.account())
+            .endpoints(self.endpoints())
+    }
+
+    pub fn client(&self) -> Result<DefaultClient> {
+        self.client_builder().build()
+    }
+
+    /// Forget every SSO session, jwcas session and jwqywx token,
+    /// the next request of a logged in client sees an expired session.
+    pub fn expire_sessions(&self) {
+        *self.state.sessions.lock().unwrap() = Sessions::default();
+    }
+
+    /// How many SSO sessions are open.
+    #[cfg(test)]
+    pub(crate) fn sso_sessions(&self) -> usize {
+        self.state.sessions.lock().unwrap().sso.len()
+    }
+
+    /// How many jwqywx tokens were given out and not expired.
+    #[cfg(test)]
+    pub(crate) fn jwqywx_tokens(&self) -> usize {
+        self.state.sessions.lock().unwrap().jwqywx.len()
+    }
+}
+
+impl Drop for MockCampus {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[derive(Default)]
+struct Sessions {
+    /// SSO ticket granting tickets, the `CASTGC` cookie
+    sso: HashSet<String>,
+    /// The `ASP.NET_SessionId` cookie
+    jwcas: HashSet<String>,
+    /// jwqywx bearer tokens
+    jwqywx: HashSet<String>,
+}
+
+struct MockState {
+    root: String,
+    account: Account,
+    webvpn: bool,
+    captcha: Option<String>,
+    verification: Option<String>,
+    code_sent: AtomicBool,
+    sessions: Mutex<Sessions>,
+    counter: AtomicU64,
+}
+
+type SharedState = Arc<MockState>;
+
+impl MockState {
+    fn id(&self, prefix: &str) -> String {
+        format!(
+            "{}-{}",
+            prefix,
+            self.counter.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    fn verify(&self, user: &str, password: &str) -> bool {
+        user == self.account.user && password == self.account.password.expose()
+    }
+
+    fn sso_session(&self, headers: &HeaderMap) -> bool {
+        cookie(headers, "CASTGC")
+            .is_some_and(|tgt| self.sessions.lock().unwrap().sso.contains(&tgt))
+    }
+
+    /// The flows follow `Location` as is, so it must be absolute.
+    fn absolute(&self, location: &str) -> String {
+        match location.starts_with('/') {
+            true => format!("{}{}", self.root, location),
+            false => location.to_string(),
+        }
+    }
+
+    fn redirect(&self, location: &str) -> Response {
+        (
+            StatusCode::FOUND,
+            [(header::LOCATION, self.absolute(location))],
+        )
+            .into_response()
+    }
+
+    fn redirect_with_cookie(&self, location: &str, cookie: String) -> Response {
+        (
+            StatusCode::FOUND,
+            [
+                (header::LOCATION, self.absolute(location)),
+                (header::SET_COOKIE, cookie),
+            ],
+        )
+            .into_response()
+    }
+
+    /// Forget the SSO session of the request.
+    fn end_sso_session(&self, headers: &HeaderMap) -> Response {
+        if let Some(tgt) = cookie(headers, "CASTGC") {
+            self.sessions.lock().unwrap().sso.remove(&tgt);
+        }
+        (
+            [(header::SET_COOKIE, "CASTGC=; Path=/; Max-Age=0")],
+            html("<html><body>已注销</body></html>".into()),
+        )
+            .into_response()
+    }
+
+    /// Log in the SSO, return the `Set-Cookie` of the new session.
+    fn create_sso_session(&self) -> String {
+        let tgt = self.id("TGT");
+        self.sessions.lock().unwrap().sso.insert(tgt.clone());
+        format!("CASTGC={}; Path=/", tgt)
+    }
+}
+
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+fn html(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], body).into_response()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::MockCampus;
+    use crate::{
+        base::{
+            app::{AppVisitor, CachedApplication},
+            captcha::CallbackCaptchaSolver,
+            client::Client,
+        },
+        error::Error,
+        impls::{
+            apps::{
+                iccard::{iccard::ICCardApplication, iccard_constants::PRESET_DORMBUILDINGS},
+                sso::jwcas::JwcasApplication,
+                wechat::jwqywx::JwqywxApplication,
+            },
+            login::{
+                sso::SSOUniversalLogin,
+                sso_status::SSOLoginStatus,
+                sso_type::{SSOLoginConnectType, VerificationChannel},
+                webvpn::WebVPNLogin,
+            },
+        },
+    };
+
+    #[tokio::test]
+    async fn sso_and_jwcas() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        assert!(client.sso_universal_login().await.unwrap().is_none());
+
+        let app = JwcasApplication::from_client_login(client.clone())
+            .await
+            .unwrap();
+        assert!(!app.get_gradeinfo_vec().await.unwrap().is_empty());
+        assert!(!app.get_techplans().await.unwrap().is_empty());
+
+        campus.expire_sessions();
+        assert!(matches!(
+            app.get_gradeinfo_vec().await,
+            Err(Error::SessionExpired(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn jwcas_relogin() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client_builder().auto_relogin(true).build().unwrap();
+        client.sso_universal_login().await.unwrap();
+        let app = JwcasApplication::from_client_login(client).await.unwrap();
+
+        campus.expire_sessions();
+        assert!(!app.get_gradeinfo_vec().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn webvpn() {
+        let campus = MockCampus::builder().webvpn(true).start().await.unwrap();
+        let client = campus.client().unwrap();
+        let info = client.sso_universal_login().await.unwrap().unwrap();
+        assert_eq!(info.username, campus.account().user);
+        let info = campus.client().unwrap().webvpn_login().await.unwrap();
+        assert_eq!(info.username, campus.account().user);
+    }
+
+    #[tokio::test]
+    async fn wrong_password() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = crate::impls::client::DefaultClient::builder()
+            .account(crate::base::client::Account::new(
+                campus.account().user,
+                "wrong",
+            ))
+            .endpoints(campus.endpoints())
+            .build()
+            .unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        assert!(matches!(
+            app.login().await,
+            Err(Error::AuthenticationFailed(_))
+        ));
+        assert!(matches!(
+            client.sso_universal_login().await,
+            Err(Error::AuthenticationFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn jwqywx() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client_builder().auto_relogin(true).build().unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        app.login().await.unwrap();
+        let term = app.terms().await.unwrap().message[0].term.clone();
+        assert!(!app.get_grades().await.unwrap().message.is_empty());
+        assert!(
+            !app.get_exams(term.clone())
+                .await
+                .unwrap()
+                .message
+                .is_empty()
+        );
+
+        campus.expire_sessions();
+        assert!(!app.get_credits_and_rank().await.unwrap().message.is_empty());
+    }
+
+    #[cfg(feature = "calendar")]
+    #[tokio::test]
+    async fn calendar() {
+        use crate::extension::calendar::{CalendarParser, parse_week_matrix};
+
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        app.login().await.unwrap();
+        let courses = parse_week_matrix(app.get_classinfo_week_matrix().await.unwrap()).unwrap();
+        assert!(!courses.is_empty());
+
+        client.sso_universal_login().await.unwrap();
+        let app = JwcasApplication::from_client_login(client).await.unwrap();
+        let courses = parse_week_matrix(app.get_classinfo_week_matrix().await.unwrap()).unwrap();
+        assert!(!courses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn iccard() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        let app = client.visit::<ICCardApplication<_, _>>().await.unwrap();
+        let area = PRESET_DORMBUILDINGS[0].clone();
+        let buildings = app.list_buildings(area.clone()).await.unwrap();
+        let building = buildings.buildingtab[0].clone();
+        let bill = app
+            .query_electricity_bill(area, building, "101")
+            .await
+            .unwrap();
+        assert_eq!(bill.room.roomid, "101");
+    }
+
+    #[tokio::test]
+    async fn captcha() {
+        let campus = MockCampus::builder().captcha("x7k2").start().await.unwrap();
+        assert!(matches!(
+            campus.client().unwrap().sso_universal_login().await,
+            Err(Error::CaptchaRequired(_))
+        ));
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let client = campus
+            .client_builder()
+            .captcha_solver(CallbackCaptchaSolver::new(move |image: Vec<u8>| {
+                // the first answer is wrong, the login resubmits a new one
+                let attempt = counter.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    match attempt {
+                        0 => Ok("wrong".to_string()),
+                        _ => Ok(String::from_utf8(image).unwrap()),
+                    }
+                }
+            }))
+            .build()
+            .unwrap();
+        client.sso_universal_login().await.unwrap();
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn verification() {
+        let campus = MockCampus::builder()
+            .verification("123456")
+            .start()
+            .await
+            .unwrap();
+        let client = campus.client().unwrap();
+        let service = format!("{}/pc/index.html", campus.url());
+        let Err(Error::VerificationRequired(verification)) =
+            client.sso_service_login(service.clone()).await
+        else {
+            panic!("no verification required");
+        };
+        assert_eq!(verification.channel, VerificationChannel::Sms);
+        assert_eq!(verification.target.as_deref(), Some("138****0000"));
+        assert!(verification.can_send_code());
+
+        client
+            .sso_send_verification_code(&verification)
+            .await
+            .unwrap();
+        let Err(Error::VerificationRequired(verification)) =
+            client.sso_verify(&verification, "000000").await
+        else {
+            panic!("wrong code accepted");
+        };
+        assert!(verification.message.is_some());
+        let response = client.sso_verify(&verification, "123456").await.unwrap();
+        assert_eq!(response.url().path(), "/pc/index.html");
+    }
+
+    #[tokio::test]
+    async fn verification_then_jwcas() {
+        let campus = MockCampus::builder()
+            .verification("123456")
+            .start()
+            .await
+            .unwrap();
+        let client = campus.client().unwrap();
+        let Err(Error::VerificationRequired(verification)) = client.sso_universal_login().await
+        else {
+            panic!("no verification required");
+        };
+        client
+            .sso_send_verification_code(&verification)
+            .await
+            .unwrap();
+        client.sso_verify(&verification, "123456").await.unwrap();
+        assert_eq!(
+            client.sso_login_connect_type().await,
+            Some(SSOLoginConnectType::COMMON)
+        );
+
+        let app = JwcasApplication::from_client_login(client).await.unwrap();
+        assert!(!app.get_gradeinfo_vec().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn logout() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        client.sso_universal_login().await.unwrap();
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        app.login().await.unwrap();
+        app.cache().await.unwrap();
+
+        client.sso_logout().await.unwrap();
+        assert!(campus.state.sessions.lock().unwrap().sso.is_empty());
+        assert!(
+            client
+                .cookies()
+                .lock()
+                .unwrap()
+                .iter_any()
+                .all(|cookie| cookie.name() != "CASTGC")
+        );
+        assert!(client.sso_login_connect_type().await.is_none());
+
+        app.logout().await.unwrap();
+        assert!(matches!(app.get_grades().await, Err(Error::NotLoggedIn)));
+        assert!(client.try_restore::<JwqywxApplication<_>>().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn concurrent_logins() {
+        let campus = MockCampus::start().await.unwrap();
+        let client = campus.client().unwrap();
+        let logins = (0..4).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.sso_universal_login().await })
+        });
+        for login in logins.collect::<Vec<_>>() {
+            login.await.unwrap().unwrap();
+        }
+        assert_eq!(campus.state.sessions.lock().unwrap().sso.len(), 1);
+
+        let app = client.visit::<JwqywxApplication<_>>().await.unwrap();
+        let logins = (0..4).map(|_| {
+            let app = app.clone();
+            tokio::spawn(async move { app.login().await.map(|message| message.token) })
+        });
+        let mut tokens = vec![];
+        for login in logins.collect::<Vec<_>>() {
+            tokens.push(login.await.unwrap().unwrap());
+        }
+        assert!(tokens.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(campus.state.sessions.lock().unwrap().jwqywx.len(), 1);
+        assert!(app.get_grades().await.is_ok());
+    }
+}
@@ -0,0 +1,178 @@
+use std::{collections::HashMap, sync::atomic::Ordering};
+
+use axum::{
+    Form, Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde_json::json;
+
+use super::{SharedState, html};
+
+pub(super) fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/sso/login", get(login_page).post(login))
+        .route("/sso/captcha.html", get(captcha))
+        .route("/sso/dynamicCode", post(send_code))
+        .route(
+            "/sso/logout",
+            get(
+                |State(state): State<SharedState>, headers: HeaderMap| async move {
+                    state.end_sso_session(&headers)
+                },
+            ),
+        )
+        .route("/sso/", get(logged_in))
+        .route("/pc/index.html", get(logged_in))
+}
+
+/// The CAS login form, the flows post the hidden inputs back.
+pub(super) fn login_form(state: &SharedState, error: Option<&str>) -> String {
+    let captcha = match state.captcha {
+        Some(_) => {
+            r#"<input name="captcha" type="text"/><img id="captchaImg" src="/sso/captcha.html"/>"#
+        }
+        None => "",
+    };
+    format!(
+        r#"<html><body>
+<form id="fm1" method="post">
+    <input id="username" name="username" type="text"/>
+    <input id="password" name="password" type="password"/>
+    <input type="hidden" name="lt" value="LT-mock"/>
+    <input type="hidden" name="execution" value="e1s1"/>
+    <input type="hidden" name="_eventId" value="submit"/>
+    {}
+    {}
+</form>
+</body></html>"#,
+        captcha,
+        error
+            .map(|error| format!(r#"<span id="errorMsg">{}</span>"#, error))
+            .unwrap_or_default()
+    )
+}
+
+/// Where to go after the login, with a service ticket if a service is given.
+fn service_location(state: &SharedState, query: &HashMap<String, String>) -> String {
+    match query.get("service").filter(|service| !service.is_empty()) {
+        Some(service) => {
+            let separator = if service.contains('?') { '&' } else { '?' };
+            format!("{}{}ticket={}", service, separator, state.id("ST"))
+        }
+        None => "/sso/".into(),
+    }
+}
+
+async fn login_page(
+    State(state): State<SharedState>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if state.sso_session(&headers) {
+        return state.redirect(&service_location(&state, &query));
+    }
+    if state.webvpn {
+        return state.redirect("/enlink/sso/login");
+    }
+    html(login_form(&state, None))
+}
+
+async fn login(
+    State(state): State<SharedState>,
+    Query(query): Query<HashMap<String, String>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    if form.get("execution").is_some_and(|e| e == VERIFY_EXECUTION) {
+        return verify_code(&state, &query, &form);
+    }
+    if let Err(error) = verify_form(&state, &form) {
+        return html(login_form(&state, Some(error)));
+    }
+    if state.verification.is_some() {
+        return html(verification_form(None));
+    }
+    state.redirect_with_cookie(
+        &service_location(&state, &query),
+        state.create_sso_session(),
+    )
+}
+
+/// The execution of the second step, after the password.
+const VERIFY_EXECUTION: &str = "e1s2";
+
+/// Posted back to the login url, so the service is kept.
+fn verification_form(error: Option<&str>) -> String {
+    format!(
+        r#"<html><body>
+<form id="fm2" method="post">
+    <span id="mobile">138****0000</span>
+    <input name="dynamicCode" type="text"/>
+    <input type="hidden" name="execution" value="{}"/>
+    <input type="hidden" name="_eventId" value="submit"/>
+    <button id="sendCode" type="button" data-url="/sso/dynamicCode">获取验证码</button>
+    {}
+</form>
+</body></html>"#,
+        VERIFY_EXECUTION,
+        error
+            .map(|error| format!(r#"<span id="errorMsg">{}</span>"#, error))
+            .unwrap_or_default()
+    )
+}
+
+async fn send_code(State(state): State<SharedState>) -> Response {
+    state.code_sent.store(true, Ordering::Relaxed);
+    Json(json!({"code": 0, "message": "发送成功"})).into_response()
+}
+
+fn verify_code(
+    state: &SharedState,
+    query: &HashMap<String, String>,
+    form: &HashMap<String, String>,
+) -> Response {
+    let sent = state.code_sent.load(Ordering::Relaxed);
+    if !sent || form.get("dynamicCode") != state.verification.as_ref() {
+        return html(verification_form(Some("动态码错误")));
+    }
+    state.redirect_with_cookie(&service_location(state, query), state.create_sso_session())
+}
+
+/// The password is posted as base64, together with the hidden inputs.
+pub(super) fn verify_form(
+    state: &SharedState,
+    form: &HashMap<String, String>,
+) -> Result<(), &'static str> {
+    if let Some(captcha) = &state.captcha
+        && form.get("captcha") != Some(captcha)
+    {
+        return Err("验证码错误");
+    }
+    let password = form
+        .get("password")
+        .and_then(|password| BASE64_STANDARD.decode(password).ok())
+        .and_then(|password| String::from_utf8(password).ok())
+        .unwrap_or_default();
+    match form.get("execution").is_some_and(|e| e == "e1s1")
+        && state.verify(form.get("username").map_or("", |u| u), &password)
+    {
+        true => Ok(()),
+        false => Err("用户名或密码错误"),
+    }
+}
+
+/// The image is the text of the captcha, enough for a test solver.
+async fn captcha(State(state): State<SharedState>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "image/png")],
+        state.captcha.clone().unwrap_or_default(),
+    )
+        .into_response()
+}
+
+async fn logged_in() -> Response {
+    html("<html><body>登录成功</body></html>".into())
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use aes::{
+    Aes128Dec,
+    cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7},
+};
+use axum::{
+    Form, Json, Router,
+    extract::{Path, Query, State},
+    http::{HeaderMap, Method, StatusCode, header},
+    response::{AppendHeaders, IntoResponse, Response},
+    routing::{any, get, post},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde_json::{Value, json};
+
+use super::{
+    SharedState, html,
+    sso::{login_form, verify_form},
+};
+
+type CbcAES128Dec = cbc::Decryptor<Aes128Dec>;
+
+pub(super) fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/enlink/sso/login", get(login_page).post(login))
+        .route("/enlink/sso/success", get(success))
+        .route("/enlink/sso/login/submit", post(submit))
+        .route(
+            "/enlink/sso/logout",
+            get(
+                |State(state): State<SharedState>, headers: HeaderMap| async move {
+                    state.end_sso_session(&headers)
+                },
+            ),
+        )
+        .route(
+            "/enlink/api/client/user/terminal/rules/{user_id}",
+            get(proxy_rules),
+        )
+        .route(
+            "/enlink/api/client/service/sucmp/findServiceByUserId/{user_id}",
+            get(services),
+        )
+        .route(
+            "/enlink/api/client/service/suvisitmp/findVisitServiceByUserId/{user_id}",
+            get(visited_services),
+        )
+        .route(
+            "/enlink/api/client/service/group/treeWithService/",
+            post(service_tree),
+        )
+        .route("/http/{host}/", any(proxied))
+        .route("/http/{host}/{*path}", any(proxied))
+}
+
+async fn login_page(State(state): State<SharedState>) -> Response {
+    html(login_form(&state, None))
+}
+
+/// The SSO form proxied by the WebVPN.
+async fn login(
+    State(state): State<SharedState>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    if let Err(error) = verify_form(&state, &form) {
+        return html(login_form(&state, Some(error)));
+    }
+    state.redirect_with_cookie("/enlink/sso/success", state.create_sso_session())
+}
+
+async fn success(State(state): State<SharedState>) -> Response {
+    (
+        [(header::SET_COOKIE, client_info(&state))],
+        html("<html><body>WebVPN</body></html>".into()),
+    )
+        .into_response()
+}
+
+/// The WebVPN login, the password is encrypted by AES-128-CBC,
+/// the key is the posted token and the iv is the reversed token.
+async fn submit(
+    State(state): State<SharedState>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    let password = form
+        .get("token")
+        .zip(form.get("password"))
+        .and_then(|(token, password)| decrypt(token, password));
+    match password {
+        Some(password) if state.verify(form.get("username").map_or("", |u| u), &password) => {
+            let session = state.create_sso_session();
+            (
+                StatusCode::FOUND,
+                [(header::LOCATION, state.absolute("/enlink/"))],
+                AppendHeaders([
+                    (header::SET_COOKIE, client_info(&state)),
+                    (header::SET_COOKIE, session),
+                ]),
+            )
+                .into_response()
+        }
+        _ => Json(json!({"code": "1", "messages": "用户名或密码错误"})).into_response(),
+    }
+}
+
+fn decrypt(token: &str, password: &str) -> Option<String> {
+    let key = token.as_bytes();
+    let iv: Vec<u8> = key.iter().rev().copied().collect();
+    if key.len() != 16 {
+        return None;
+    }
+    let mut buf = BASE64_STANDARD.decode(password).ok()?;
+    let plaintext = CbcAES128Dec::new(key.into(), iv.as_slice().into())
+        .decrypt_padded_mut::<Pkcs7>(&mut buf)
+        .ok()?;
+    String::from_utf8(plaintext.to_vec()).ok()
+}
+
+/// `clientInfo` holds the base64 of the login info.
+fn client_info(state: &SharedState) -> String {
+    let info = json!({
+        "username": state.account.user,
+        "sid": state.id("sid"),
+        "userId": format!("user-{}", state.account.user),
+        "loginKey": state.id("key"),
+    });
+    format!(
+        "clientInfo={}; Path=/",
+        BASE64_STANDARD.encode(info.to_string())
+    )
+}
+
+async fn proxy_rules(Path(user_id): Path<String>) -> Json<Value> {
+    Json(json!({
+        "code": "0",
+        "messages": "success",
+        "data": {
+            "token": format!("token-{}", user_id),
+            "server": "127.0.0.1",
+            "tunnel_free_interval": 300,
+            "tunnel_free_status": false,
+            "spa_status": false,
+            "fwd_status": true,
+            "spa_port": "0",
+            "admin_port": "0",
+            "gateway_list": [{
+                "id": "gateway-1",
+                "dns": "202.195.100.1",
+                "whiteList": ["*.cczu.edu.cn"],
+                "inIpList": ["219.230.159.0/24", "219.230.159.132"],
+                "inIpListByGatewayMap": {"gateway-1": ["10.10.0.0-10.10.1.255"]}
+            }]
+        }
+    }))
+}
+
+/// Not a PNG, but bytes which aren't UTF-8.
+const LOGO: &[u8] = b"\x89PNG\r\n\x1a\n\xff\xfe";
+
+/// A campus page proxied by the WebVPN, only served to a logged in user.
+async fn proxied(
+    State(state): State<SharedState>,
+    Path(params): Path<HashMap<String, String>>,
+    method: Method,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if !state.sso_session(&headers) {
+        return state.redirect("/enlink/sso/login");
+    }
+    let path = params.get("path").map_or("", |path| path);
+    let prefix = format!("/http/{}", params["host"]);
+    match path {
+        "moved" => state.redirect(&format!("{}/page", prefix)),
+        "logo.png" => ([(header::CONTENT_TYPE, "image/png")], LOGO).into_response(),
+        // 教务 in GBK, like the pages of the jwcas
+        "gbk" => {
+            let mut page = format!(r#"<html><body><a href="{prefix}/next">"#).into_bytes();
+            page.extend_from_slice(b"\xbd\xcc\xce\xf1</a></body></html>");
+            ([(header::CONTENT_TYPE, "text/html; charset=gbk")], page).into_response()
+        }
+        _ => html(format!(
+            r#"<html><body><p>{method} /{path} {body}</p><a href="{prefix}/next">next</a><img src="{root}{prefix}/logo.png"></body></html>"#,
+            root = state.root
+        )),
+    }
+}
+
+/// The host of the library is only given by its proxied `urlPlus`.
+fn service_list() -> Vec<Value> {
+    let gateway = json!({
+        "id": "gateway-1",
+        "name": "gateway",
+        "uniqueNo": "1",
+        "server": "127.0.0.1",
+        "description": "",
+        "type": "web",
+        "adminAddr": "",
+        "nginxPort": "443",
+        "connectState": "1",
+        "publicServer": "127.0.0.1"
+    });
+    vec![
+        json!({
+            "id": "service-1",
+            "name": "教务系统",
+            "icon": "",
+            "server": "http://219.230.159.132",
+            "description": "",
+            "type": "http",
+            "urlPlus": null,
+            "hostMd5": "dc2d086cb5b297c15e661687e73c1549",
+            "gatewayVo": gateway
+        }),
+        json!({
+            "id": "service-2",
+            "name": "图书馆",
+            "icon": "",
+            "server": "lib.cczu.edu.cn:8080",
+            "description": "",
+            "type": "http",
+            "urlPlus": "/http/webvpn5f1e0b2a/index",
+            "hostMd5": null,
+            "gatewayVo": gateway
+        }),
+    ]
+}
+
+/// The services whose name contains `name`.
+fn services_named(name: &str) -> Vec<Value> {
+    service_list()
+        .into_iter()
+        .filter(|service| service["name"].as_str().is_some_and(|n| n.contains(name)))
+        .collect()
+}
+
+fn success_json(data: Value) -> Json<Value> {
+    Json(json!({"code": "0", "messages": "success", "data": data}))
+}
+
+async fn services(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+    let name = query.get("name").map_or("", |name| name);
+    success_json(Value::Array(services_named(name)))
+}
+
+/// Only the library was visited.
+async fn visited_services(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+    let name = query.get("name").map_or("", |name| name);
+    let visited = services_named(name)
+        .into_iter()
+        .filter(|service| service["id"] == "service-2")
+        .collect();
+    success_json(Value::Array(visited))
+}
+
+/// `教学` holds the jwcas, `资源/图书` the library.
+async fn service_tree(Json(body): Json<Value>) -> Json<Value> {
+    let name = body["serviceNameLike"].as_str().unwrap_or_default();
+    let services = services_named(name);
+    let pick = |id: &str| -> Vec<Value> {
+        services
+            .iter()
+            .filter(|service| service["id"] == id)
+            .cloned()
+            .collect()
+    };
+    let (jwcas, library) = (pick("service-1"), pick("service-2"));
+    let group = |title: &str, services: Vec<Value>, children: Vec<Value>| json!({"title": title, "key": title, "children": children, "serviceList": services});
+    success_json(group(
+        "全部",
+        vec![],
+        vec![
+            group("教学", jwcas, vec![]),
+            group("资源", vec![], vec![group("图书", library, vec![])]),
+        ],
+    ))
+}